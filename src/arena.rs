@@ -0,0 +1,136 @@
+use crate::board::Board;
+use crate::engine::Engine;
+use rand::{rngs::StdRng, seq::IteratorRandom, SeedableRng};
+
+/// Outcomes of an engine's games against one opponent, from the engine's side.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Record {
+    pub games: usize,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    /// Illegal moves made by the engine. Each one forfeits its game.
+    pub illegal_moves: usize,
+    /// Moves made by both sides over all games.
+    pub plies: usize
+}
+
+impl Record {
+    /// `count` as a fraction of the games played.
+    pub fn rate(&self, count: usize) -> f64 {
+        count as f64 / self.games.max(1) as f64
+    }
+
+    /// Average number of moves per game.
+    pub fn avg_length(&self) -> f64 {
+        self.plies as f64 / self.games.max(1) as f64
+    }
+}
+
+/// Random moves played at the start of every game before the engines take over.
+/// Two deterministic engines would otherwise play the same game every time.
+pub struct Openings {
+    pub plies: usize,
+    rng: StdRng
+}
+
+impl Openings {
+    /// Openings of `plies` random moves, drawn from a generator seeded with `seed`.
+    pub fn new(plies: usize, seed: u64) -> Openings {
+        Openings { plies, rng: StdRng::seed_from_u64(seed) }
+    }
+
+    fn play(&mut self) -> Board {
+        let mut board = Board::new();
+        for _ in 0..self.plies {
+            if board.finished() {
+                break
+            }
+            let col = board.available_columns().into_iter().choose(&mut self.rng).unwrap();
+            board.place(col, board.to_move()).unwrap();
+        }
+        board
+    }
+}
+
+/// Plays one game with `engine` as `piece` from the next of `openings` and adds
+/// its outcome to `record`. A side that plays an illegal move loses on the spot.
+pub fn play_game(engine: &mut dyn Engine, opponent: &mut dyn Engine, piece: u8, openings: &mut Openings, record: &mut Record) {
+    let mut board = openings.play();
+    let mut plies = 0;
+
+    let winner = loop {
+        if board.finished() {
+            break board.winner()
+        }
+
+        let turn = board.to_move();
+        let mover: &mut dyn Engine = if turn == piece { &mut *engine } else { &mut *opponent };
+        let col = mover.choose_move(&board, turn);
+        if board.place(col, turn).is_err() {
+            if turn == piece {
+                record.illegal_moves += 1;
+            }
+            break Some(turn ^ 3)
+        }
+        plies += 1;
+    };
+
+    record.games += 1;
+    record.plies += plies;
+    match winner {
+        Some(winner) if winner == piece => record.wins += 1,
+        Some(_) => record.losses += 1,
+        None => record.draws += 1
+    }
+}
+
+/// Plays `games` games with `engine` as `piece` against `opponent`.
+pub fn play_match(engine: &mut dyn Engine, opponent: &mut dyn Engine, piece: u8, games: usize, openings: &mut Openings) -> Record {
+    let mut record = Record::default();
+    for _ in 0..games {
+        play_game(engine, opponent, piece, openings, &mut record);
+    }
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{Column, SearchEngine};
+
+    #[test]
+    fn test_illegal_move_forfeits_the_game() {
+        let mut first = Column(0);
+        let mut second = Column(0);
+        let record = play_match(&mut first, &mut second, 1, 2, &mut Openings::new(0, 0));
+        // Column 0 fills after six moves and the first player's seventh is illegal.
+        assert_eq!(record, Record { games: 2, losses: 2, illegal_moves: 2, plies: 12, ..Default::default() });
+    }
+
+    #[test]
+    fn test_search_beats_a_stubborn_opponent_as_both_colors() {
+        let mut search = SearchEngine::new(2);
+        for piece in [1, 2] {
+            let record = play_match(&mut search, &mut Column(0), piece, 1, &mut Openings::new(0, 0));
+            assert_eq!(record.wins, 1);
+            assert_eq!(record.illegal_moves, 0);
+        }
+    }
+
+    #[test]
+    fn test_openings_vary_games_between_deterministic_engines() {
+        let mut first = SearchEngine::new(2);
+        let mut second = SearchEngine::new(2);
+        let lengths: Vec<usize> = (0..5)
+            .map(|_| play_match(&mut first, &mut second, 1, 1, &mut Openings::new(0, 0)).plies)
+            .collect();
+        assert!(lengths.windows(2).all(|w| w[0] == w[1]));
+
+        let mut openings = Openings::new(2, 0);
+        let lengths: Vec<usize> = (0..5)
+            .map(|_| play_match(&mut first, &mut second, 1, 1, &mut openings).plies)
+            .collect();
+        assert!(lengths.windows(2).any(|w| w[0] != w[1]));
+    }
+}
@@ -7,7 +7,7 @@ pub enum BoardPlaceError {
     InvalidPiece
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct Board {
     slots: [[u8; HEIGHT]; WIDTH]
 }
@@ -166,6 +166,113 @@ impl Board {
         cols
     }
 
+    /// Empty cells, as `(col, row)`, that would complete a four for `piece` if it
+    /// held them. Cells that cannot be played yet are included.
+    pub fn threats(&self, piece: u8) -> Vec<(usize, usize)> {
+        let mut cells = Vec::new();
+        for col in 0..WIDTH {
+            for row in 0..HEIGHT {
+                if self.slots[col][row] == 0 && self.completes_four(col, row, piece) {
+                    cells.push((col, row));
+                }
+            }
+        }
+        cells
+    }
+
+    /// Columns where `piece` would win immediately.
+    pub fn winning_moves(&self, piece: u8) -> Vec<usize> {
+        self.available_columns()
+            .into_iter()
+            .filter(|&col| {
+                let row = self.first_available_row_for_column(col).unwrap();
+                self.completes_four(col, row, piece)
+            })
+            .collect()
+    }
+
+    /// Columns where `piece` wins at once or leaves the opponent no immediate
+    /// win. Empty when every move loses.
+    pub fn non_losing_moves(&self, piece: u8) -> Vec<usize> {
+        let wins = self.winning_moves(piece);
+        if !wins.is_empty() {
+            return wins
+        }
+
+        self.available_columns()
+            .into_iter()
+            .filter(|&col| {
+                let mut next = self.clone();
+                let _ = next.place(col, piece);
+                next.winning_moves(piece ^ 3).is_empty()
+            })
+            .collect()
+    }
+
+    /// Whether `piece` in `(col, row)` would line up four, assuming the cell is free.
+    fn completes_four(&self, col: usize, row: usize, piece: u8) -> bool {
+        let count = |dc: isize, dr: isize| {
+            let mut n = 0;
+            let (mut c, mut r) = (col as isize + dc, row as isize + dr);
+            while c >= 0 && c < WIDTH as isize && r >= 0 && r < HEIGHT as isize
+                && self.slots[c as usize][r as usize] == piece
+            {
+                n += 1;
+                c += dc;
+                r += dr;
+            }
+            n
+        };
+
+        [(1, 0), (0, 1), (1, 1), (1, -1)]
+            .iter()
+            .any(|&(dc, dr)| count(dc, dr) + count(-dc, -dr) >= 3)
+    }
+
+    /// Piece in the given cell, 0 when empty. Row 0 is the bottom row.
+    pub fn piece_at(&self, col: usize, row: usize) -> u8 {
+        self.slots[col][row]
+    }
+
+    /// Number of pieces placed so far.
+    pub fn plies(&self) -> usize {
+        self.slots.iter().flatten().filter(|&&piece| piece != 0).count()
+    }
+
+    /// Piece whose turn it is, assuming piece 1 moved first.
+    pub fn to_move(&self) -> u8 {
+        if self.plies().is_multiple_of(2) { 1 } else { 2 }
+    }
+
+    /// Unique 49-bit encoding of the position. Each column takes 7 bits: a 1 bit
+    /// marking its height followed by one bit per piece, set for piece 2.
+    pub fn key(&self) -> u64 {
+        let mut key = 0;
+        for col in 0..WIDTH {
+            let mut bits = 1u64;
+            for row in (0..HEIGHT).rev() {
+                let piece = self.slots[col][row];
+                if piece != 0 || bits != 1 {
+                    bits = (bits << 1) | (piece == 2) as u64;
+                }
+            }
+            key |= bits << (col * (HEIGHT + 1));
+        }
+        key
+    }
+
+    /// The same position reflected left to right.
+    pub fn mirrored(&self) -> Board {
+        let mut slots = self.slots;
+        slots.reverse();
+        Board { slots }
+    }
+
+    /// Key shared by a position and its mirror image, so symmetric positions are stored once.
+    pub fn canonical_key(&self) -> u64 {
+        self.key().min(self.mirrored().key())
+    }
+
     pub fn flatten(&self) -> Vec<u8> {
         let mut flat = Vec::new();
         for col in 0..WIDTH {
@@ -302,6 +409,62 @@ mod tests {
         assert_eq!(board.winner().unwrap(), 1);
     }
 
+    #[test]
+    fn test_canonical_key_matches_mirror_image() {
+        let mut board = setup_board();
+        let _ = board.place(0, 1);
+        let _ = board.place(1, 2);
+        let mirrored = board.mirrored();
+        assert_ne!(board.key(), mirrored.key());
+        assert_eq!(board.canonical_key(), mirrored.canonical_key());
+    }
+
+    #[test]
+    fn test_key_distinguishes_pieces_and_heights() {
+        let mut one = setup_board();
+        let mut two = setup_board();
+        let _ = one.place(0, 1);
+        let _ = two.place(0, 2);
+        assert_ne!(one.key(), two.key());
+        assert_ne!(one.key(), setup_board().key());
+    }
+
+    #[test]
+    fn test_winning_moves_and_threats() {
+        let mut board = setup_board();
+        for col in 1..4 {
+            let _ = board.place(col, 1);
+        }
+        assert_eq!(board.winning_moves(1), vec![0, 4]);
+        assert!(board.winning_moves(2).is_empty());
+        assert_eq!(board.threats(1), vec![(0, 0), (4, 0)]);
+    }
+
+    #[test]
+    fn test_threats_include_unplayable_cells() {
+        let mut board = setup_board();
+        for i in 0..3 {
+            for _ in 0..=i {
+                let _ = board.place(i, 2);
+            }
+            let _ = board.place(i, 1);
+        }
+        assert!(board.threats(1).contains(&(3, 4)));
+        assert!(board.winning_moves(1).is_empty());
+    }
+
+    #[test]
+    fn test_non_losing_moves_must_block() {
+        let mut board = setup_board();
+        for col in 0..3 {
+            let _ = board.place(col, 1);
+            let _ = board.place(col, 2);
+        }
+        let _ = board.place(6, 1);
+        // Piece 2 to move must block piece 1 on column 3.
+        assert_eq!(board.non_losing_moves(2), vec![3]);
+    }
+
     #[test]
     fn test_full_returns_true_when_full() {
         let mut board = setup_board();
@@ -0,0 +1,154 @@
+use crate::board::{Board, WIDTH};
+use crate::search;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+
+const MAGIC: &[u8; 4] = b"C4BK";
+const VERSION: u8 = 1;
+/// Bytes per entry: a 7-byte canonical key, the column and a 2-byte score.
+const ENTRY_SIZE: usize = 10;
+
+/// Best moves for early positions, keyed by `Board::canonical_key`.
+///
+/// On disk a book is the magic `C4BK`, a version byte, the ply depth it was
+/// built to, a little-endian `u32` entry count and then the entries sorted by key.
+pub struct OpeningBook {
+    depth: u8,
+    entries: HashMap<u64, (u8, i16)>
+}
+
+impl OpeningBook {
+    /// Searches every position reachable within `plies` moves to `search_depth`
+    /// plies, which is exact once the search reaches the end of the game.
+    pub fn build(plies: u8, search_depth: u32) -> OpeningBook {
+        let mut entries = HashMap::new();
+        let mut frontier = vec![Board::new()];
+
+        for _ in 0..=plies {
+            let mut seen = HashSet::new();
+            let mut next = Vec::new();
+
+            for board in frontier {
+                if board.finished() || !seen.insert(board.canonical_key()) {
+                    continue
+                }
+
+                // Store the move for the canonical orientation.
+                let canonical = if board.key() == board.canonical_key() { board } else { board.mirrored() };
+                let piece = canonical.to_move();
+                if let Some((col, score)) = search::best_move(&canonical, piece, search_depth) {
+                    entries.insert(canonical.canonical_key(), (col as u8, score as i16));
+                }
+
+                for col in canonical.available_columns() {
+                    let mut child = canonical.clone();
+                    let _ = child.place(col, piece);
+                    next.push(child);
+                }
+            }
+            frontier = next;
+        }
+
+        OpeningBook { depth: plies, entries }
+    }
+
+    /// Ply depth the book was built to.
+    pub fn depth(&self) -> u8 {
+        self.depth
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Best column and its score for `board`, if the position is in the book.
+    pub fn lookup(&self, board: &Board) -> Option<(usize, i32)> {
+        let key = board.key();
+        let canonical = board.canonical_key();
+        let (col, score) = self.entries.get(&canonical)?;
+        let col = *col as usize;
+
+        if key == canonical {
+            Some((col, *score as i32))
+        } else {
+            Some((WIDTH - 1 - col, *score as i32))
+        }
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut keys: Vec<&u64> = self.entries.keys().collect();
+        keys.sort();
+
+        let mut bytes = Vec::with_capacity(10 + keys.len() * ENTRY_SIZE);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(self.depth);
+        bytes.extend_from_slice(&(keys.len() as u32).to_le_bytes());
+        for key in keys {
+            let (col, score) = self.entries[key];
+            bytes.extend_from_slice(&key.to_le_bytes()[..7]);
+            bytes.push(col);
+            bytes.extend_from_slice(&score.to_le_bytes());
+        }
+        fs::write(path, bytes)
+    }
+
+    pub fn load(path: &str) -> io::Result<OpeningBook> {
+        let bytes = fs::read(path)?;
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, msg));
+
+        if bytes.len() < 10 || &bytes[..4] != MAGIC {
+            return Err(invalid("not an opening book"))
+        }
+        if bytes[4] != VERSION {
+            return Err(invalid("unsupported opening book version"))
+        }
+
+        let depth = bytes[5];
+        let count = u32::from_le_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]) as usize;
+        let body = &bytes[10..];
+        if body.len() != count * ENTRY_SIZE {
+            return Err(invalid("truncated opening book"))
+        }
+
+        let mut entries = HashMap::with_capacity(count);
+        for entry in body.chunks_exact(ENTRY_SIZE) {
+            let mut key = [0u8; 8];
+            key[..7].copy_from_slice(&entry[..7]);
+            let score = i16::from_le_bytes([entry[8], entry[9]]);
+            entries.insert(u64::from_le_bytes(key), (entry[7], score));
+        }
+
+        Ok(OpeningBook { depth, entries })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let book = OpeningBook::build(2, 2);
+        let path = std::env::temp_dir().join("connect_four_book_test.bin");
+        let path = path.to_str().unwrap();
+        assert!(book.save(path).is_ok());
+
+        let loaded = OpeningBook::load(path).unwrap();
+        let _ = fs::remove_file(path);
+        assert_eq!(loaded.depth(), 2);
+        assert_eq!(loaded.len(), book.len());
+        assert_eq!(loaded.lookup(&Board::new()), book.lookup(&Board::new()));
+    }
+
+    #[test]
+    fn test_lookup_mirrors_move_for_mirrored_position() {
+        let book = OpeningBook::build(1, 2);
+        let mut board = Board::new();
+        let _ = board.place(0, 1);
+        let (col, _) = book.lookup(&board).unwrap();
+        let (mirrored_col, _) = book.lookup(&board.mirrored()).unwrap();
+        assert_eq!(mirrored_col, WIDTH - 1 - col);
+    }
+}
@@ -1,18 +1,100 @@
 use crate::board::Board;
-use crate::bot::env::{Env, Step};
-use tch::{nn, nn::OptimizerConfig, nn::Sequential, Kind::Float, Tensor};
+use crate::bot::config::TrainConfig;
+use crate::bot::env::{legal_mask, observation, Step, WIN_REWARD};
+use crate::bot::metadata::{ModelError, ModelMetadata};
+use crate::bot::league::League;
+use crate::bot::metrics::{self, EpochMetrics, MetricsLog};
+use crate::bot::optim::Adam;
+use crate::bot::vec_env::VecEnv;
+use crate::dataset::DatasetError;
+use crate::engine::{Analysis, Engine, RandomEngine, SearchEngine, SpecError};
+pub use crate::mlp::{Activation, Inference};
+use crate::mlp::{Layer, Mlp};
+use rand::{distributions::WeightedIndex, prelude::Distribution, rngs::StdRng, SeedableRng};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Instant;
+use tch::{nn, nn::Sequential, Kind::Float, TchError, Tensor};
+
+/// Logit assigned to illegal columns. Large enough to zero their probability
+/// without producing NaNs once multiplied by the one-hot action mask.
+const ILLEGAL_LOGIT: f64 = -1e9;
+
+/// Shape of the policy network. Weights saved with one architecture can only be
+/// loaded into a `Bot` built with the same one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Architecture {
+    pub hidden: Vec<i64>,
+    pub activation: Activation,
+    /// Adds a value head on top of the hidden layers, as trained by PPO.
+    pub value_head: bool
+}
+
+impl Default for Architecture {
+    fn default() -> Architecture {
+        Architecture { hidden: vec![32], activation: Activation::Tanh, value_head: false }
+    }
+}
+
+/// Hidden layers shared by a policy head and an optional value head. Applying the
+/// network as a module yields the policy logits.
+#[derive(Debug)]
+pub struct Network {
+    trunk: Sequential,
+    policy: nn::Linear,
+    value: Option<nn::Linear>
+}
+
+impl Network {
+    /// Estimated return for each observation in the batch, if the network has a value head.
+    pub fn value(&self, xs: &Tensor) -> Option<Tensor> {
+        self.value.as_ref().map(|value| xs.apply(&self.trunk).apply(value).squeeze_dim(1))
+    }
+}
+
+impl nn::Module for Network {
+    fn forward(&self, xs: &Tensor) -> Tensor {
+        xs.apply(&self.trunk).apply(&self.policy)
+    }
+}
 
 pub struct Bot {
-    model: Sequential,
-    vs: nn::VarStore
+    model: Network,
+    vs: nn::VarStore,
+    architecture: Architecture,
+    /// Training epochs behind the current weights, recorded in the model metadata.
+    epochs: usize,
+    /// Value head output for a won position, see `ModelMetadata::value_scale`.
+    value_scale: f64,
+    inference: Inference,
+    rng: StdRng
 }
 
-fn model(p: &nn::Path, input_shape: &[i64], nact: i64) -> Sequential {
-    let nin = input_shape.len() as i64;
-    nn::seq()
-        .add(nn::linear(p / "lin1", nin, 32, Default::default()))
-        .add_fn(|xs| xs.tanh())
-        .add(nn::linear(p / "lin2", 32, nact, Default::default()))
+pub fn model(p: &nn::Path, input_shape: &[i64], nact: i64, architecture: &Architecture) -> Network {
+    let mut nin = input_shape.len() as i64;
+    let mut seq = nn::seq();
+    for (i, &size) in architecture.hidden.iter().enumerate() {
+        seq = seq.add(nn::linear(p / format!("lin{}", i + 1), nin, size, Default::default()));
+        seq = match architecture.activation {
+            Activation::Tanh => seq.add_fn(|xs| xs.tanh()),
+            Activation::Relu => seq.add_fn(|xs| xs.relu())
+        };
+        nin = size;
+    }
+    let out = format!("lin{}", architecture.hidden.len() + 1);
+    Network {
+        trunk: seq,
+        policy: nn::linear(p / out, nin, nact, Default::default()),
+        value: architecture.value_head.then(|| nn::linear(p / "value", nin, 1, Default::default()))
+    }
+}
+
+/// Replaces the logits of illegal columns so they can never be selected.
+pub fn mask_logits(logits: &Tensor, mask: &Tensor) -> Tensor {
+    logits.masked_fill(&mask.logical_not(), ILLEGAL_LOGIT)
 }
 
 fn accumulate_rewards(steps: &[Step<i64>]) -> Vec<f64> {
@@ -28,40 +110,203 @@ fn accumulate_rewards(steps: &[Step<i64>]) -> Vec<f64> {
     rewards
 }
 
+/// Why a training run stopped early.
+#[derive(Debug)]
+pub enum TrainError {
+    Tch(TchError),
+    Io(io::Error),
+    /// The checkpoint to resume from could not be loaded.
+    Model(ModelError),
+    /// A league opponent could not be built.
+    Spec(SpecError),
+    /// The labelled positions for supervised training could not be read from the file.
+    Dataset(String, DatasetError)
+}
+
+impl fmt::Display for TrainError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TrainError::Tch(err) => write!(f, "{}", err),
+            TrainError::Io(err) => write!(f, "{}", err),
+            TrainError::Model(err) => write!(f, "{}", err),
+            TrainError::Spec(err) => write!(f, "{}", err),
+            TrainError::Dataset(path, err) => write!(f, "{}: {}", path, err)
+        }
+    }
+}
+
+impl From<TchError> for TrainError {
+    fn from(err: TchError) -> TrainError {
+        TrainError::Tch(err)
+    }
+}
+
+impl From<io::Error> for TrainError {
+    fn from(err: io::Error) -> TrainError {
+        TrainError::Io(err)
+    }
+}
+
+impl From<ModelError> for TrainError {
+    fn from(err: ModelError) -> TrainError {
+        TrainError::Model(err)
+    }
+}
+
+impl From<SpecError> for TrainError {
+    fn from(err: SpecError) -> TrainError {
+        TrainError::Spec(err)
+    }
+}
+
+/// Writes the model weights, optimizer state and the number of completed epochs to
+/// `path`, with the model metadata next to it so `Bot::open_checkpoint` can read it back.
+pub fn save_checkpoint(path: &str, epochs: usize, bot: &Bot, opt: &Adam) -> Result<(), TchError> {
+    ModelMetadata { value_scale: bot.value_scale, ..ModelMetadata::new(&bot.architecture, epochs) }
+        .save(&ModelMetadata::path_for(path))?;
+    let mut tensors = vec![(String::from("epoch"), Tensor::from_slice(&[epochs as i64]))];
+    for (name, var) in bot.vs.variables() {
+        tensors.push((format!("model.{}", name), var));
+    }
+    for (name, state) in opt.state() {
+        tensors.push((format!("adam.{}", name), state));
+    }
+    Tensor::save_multi(&tensors, path)
+}
+
+/// Restores a checkpoint written by `save_checkpoint`, returning the number of completed epochs.
+/// Checkpoints whose metadata describes a different network than `bot` are rejected.
+pub fn load_checkpoint(path: &str, bot: &mut Bot, opt: &mut Adam) -> Result<usize, ModelError> {
+    let metadata_path = ModelMetadata::path_for(path);
+    if Path::new(&metadata_path).exists() {
+        let metadata = ModelMetadata::load(&metadata_path)?;
+        metadata.check()?;
+        if metadata.architecture != bot.architecture {
+            return Err(ModelError::Incompatible(format!(
+                "checkpoint network is {:?}, expected {:?}",
+                metadata.architecture, bot.architecture
+            )))
+        }
+    }
+
+    let mut tensors: HashMap<String, Tensor> = Tensor::load_multi(path)?.into_iter().collect();
+    let mut take = |name: &str| {
+        tensors
+            .remove(name)
+            .ok_or_else(|| TchError::TensorNameNotFound(name.to_string(), path.to_string()))
+    };
+
+    let epochs = take("epoch")?.int64_value(&[0]) as usize;
+    for (name, mut var) in bot.vs.variables() {
+        let value = take(&format!("model.{}", name))?;
+        tch::no_grad(|| var.f_copy_(&value))?;
+    }
+
+    let state: Vec<(String, Tensor)> = tensors
+        .into_iter()
+        .filter_map(|(name, t)| name.strip_prefix("adam.").map(|n| (n.to_string(), t)))
+        .collect();
+    opt.load_state(&state)?;
+
+    Ok(epochs)
+}
+
 /// Trains an agent using the policy gradient algorithm.
-pub fn train() {
-    let bot = Bot::new();
-    let mut env = Env::new();
-    println!("action space: {:?}", env.action_space());
-    println!("observation space: {:?}", env.observation_space());
+///
+/// When `resume` names a checkpoint, training continues from the epoch it was saved at.
+pub fn train(config: &TrainConfig, resume: Option<&str>) -> Result<(), TrainError> {
+    if let Some(seed) = config.seed {
+        tch::manual_seed(seed as i64);
+    }
+
+    let mut bot = Bot::with_architecture(&config.architecture);
+    bot.set_value_scale(config.rewards.win);
+    let mut league = League::new(&config.league, config.league_checkpoints);
+    let league_seed = config.seed.unwrap_or_else(rand::random);
+    let mut envs = VecEnv::new(config.num_envs, &league, config.threads, league_seed)?;
+    envs.set_first_move_rate(config.first_move_rate);
+    envs.set_rewards(&config.rewards);
+    envs.set_illegal_moves(config.illegal_moves);
+    println!("action space: {:?}", envs.action_space());
+    println!("observation space: {:?}", envs.observation_space());
 
-    let mut opt = nn::Adam::default().build(&bot.vs, 1e-3).unwrap();
+    let mut opt = Adam::new(&bot.vs, config.learning_rate);
     println!("{:?}", bot.model);
 
-    for epoch_idx in 0..1000 {
-        let mut obs = env.reset();
-        let mut steps: Vec<Step<i64>> = vec![];
-        // Perform some rollouts with the current model.
-        loop {
-            let action = tch::no_grad(|| {
-                obs.unsqueeze(0).apply(&bot.model).softmax(1, Float).multinomial(1, true)
+    let start_epoch = match resume {
+        Some(path) => {
+            let epochs = load_checkpoint(path, &mut bot, &mut opt)?;
+            println!("Resuming from {} after {} epochs", path, epochs);
+            epochs
+        },
+        None => 0
+    };
+
+    if config.checkpoint_every > 0 {
+        fs::create_dir_all(&config.checkpoint_dir)?;
+    }
+
+    let mut log = match &config.metrics {
+        Some(path) => Some(MetricsLog::open(path)?),
+        None => None
+    };
+    let mut random = config.seed.map_or_else(RandomEngine::new, RandomEngine::seeded);
+    let mut solver = SearchEngine::new(config.solver_depth);
+    let started = Instant::now();
+    // Win rates are measured with the move the network rates highest.
+    bot.set_inference(Inference::Greedy);
+
+    for epoch_idx in start_epoch..config.epochs {
+        let seed = if epoch_idx == start_epoch { config.seed } else { None };
+        let mut obs = envs.reset(seed);
+        let mut trajectories: Vec<Vec<Step<i64>>> = (0..config.num_envs).map(|_| vec![]).collect();
+        let mut collected = 0;
+        let mut wins = 0;
+        let mut finished_episodes = 0;
+        // Perform some rollouts with the current model, one batched forward pass per step.
+        // Keep going until at least one episode has finished, since unfinished ones are
+        // dropped below; truncation guarantees this within `MAX_EPISODE_STEPS`.
+        while collected < config.steps_per_epoch || finished_episodes == 0 {
+            let masks = envs.legal_masks();
+            let actions = tch::no_grad(|| {
+                mask_logits(&obs.apply(&bot.model), &masks).softmax(1, Float).multinomial(1, true)
             });
-            let action = i64::try_from(action).unwrap();
-            let step = env.step(action);
-            steps.push(step.copy_with_obs(&obs));
-            obs = if step.is_done { env.reset() } else { step.obs };
-            if step.is_done && steps.len() > 5000 {
-                break;
+            let actions = Vec::<i64>::try_from(actions.squeeze_dim(1)).unwrap();
+            let step = envs.step(&actions);
+            for (i, trajectory) in trajectories.iter_mut().enumerate() {
+                trajectory.push(Step {
+                    obs: obs.get(i as i64),
+                    mask: masks.get(i as i64),
+                    action: actions[i],
+                    reward: step.rewards[i],
+                    is_done: step.terminated[i] || step.truncated[i]
+                });
             }
+            wins += step.infos.iter().filter(|info| info.winner == Some(info.agent_piece)).count();
+            let finished: Vec<bool> = step.terminated.iter().zip(&step.truncated).map(|(a, b)| *a || *b).collect();
+            league.record(&step.infos, &finished);
+            finished_episodes += finished.iter().filter(|done| **done).count();
+            collected += config.num_envs;
+            obs = step.obs;
+        }
+
+        // Episodes still running when the rollout stopped have no final reward, drop them.
+        let mut steps: Vec<Step<i64>> = vec![];
+        for mut trajectory in trajectories {
+            let complete = trajectory.iter().rposition(|s| s.is_done).map_or(0, |i| i + 1);
+            trajectory.truncate(complete);
+            steps.extend(trajectory);
         }
         let sum_r: f64 = steps.iter().map(|s| s.reward).sum();
         let episodes: i64 = steps.iter().map(|s| s.is_done as i64).sum();
         println!(
-            "epoch: {:<3} episodes: {:<5} avg reward per episode: {:.2}",
+            "epoch: {:<3} episodes: {:<5} avg reward per episode: {:.2} wins: {:<5}",
             epoch_idx,
             episodes,
-            sum_r / episodes as f64
+            sum_r / episodes as f64,
+            wins
         );
+        println!("league win rates: {}", league.summary());
 
         // Train the model via policy gradient on the rollout data.
         let batch_size = steps.len() as i64;
@@ -70,38 +315,240 @@ pub fn train() {
         let rewards = accumulate_rewards(&steps);
         let rewards = Tensor::from_slice(&rewards).to_kind(Float);
         let action_mask =
-            Tensor::zeros([batch_size, env.action_space()], tch::kind::FLOAT_CPU).scatter_value(1, &actions, 1.0);
+            Tensor::zeros([batch_size, envs.action_space()], tch::kind::FLOAT_CPU).scatter_value(1, &actions, 1.0);
+        let masks: Vec<Tensor> = steps.iter().map(|s| s.mask.shallow_clone()).collect();
         let obs: Vec<Tensor> = steps.into_iter().map(|s| s.obs).collect();
-        let logits = Tensor::stack(&obs, 0).apply(&bot.model);
-        let log_probs =
-            (action_mask * logits.log_softmax(1, Float)).sum_dim_intlist(1, false, Float);
+        let logits = mask_logits(&Tensor::stack(&obs, 0).apply(&bot.model), &Tensor::stack(&masks, 0));
+        let all_log_probs = logits.log_softmax(1, Float);
+        let log_probs = (action_mask * &all_log_probs).sum_dim_intlist(1, false, Float);
         let loss = -(rewards * log_probs).mean(Float);
-        opt.backward_step(&loss)
+        opt.backward_step(&loss);
+
+        if let Some(log) = log.as_mut() {
+            let entropy = tch::no_grad(|| {
+                -(all_log_probs.exp() * &all_log_probs).sum_dim_intlist(1, false, Float).mean(Float)
+            });
+            log.write(&EpochMetrics {
+                epoch: epoch_idx,
+                episodes,
+                avg_reward: sum_r / episodes as f64,
+                loss: f64::try_from(&loss)?,
+                entropy: f64::try_from(entropy)?,
+                win_rate_random: metrics::win_rate(&mut bot, &mut random, config.eval_games),
+                win_rate_solver: metrics::win_rate(&mut bot, &mut solver, config.eval_games),
+                wall_time: started.elapsed().as_secs_f64()
+            })?;
+        }
+
+        if config.checkpoint_every > 0 && (epoch_idx + 1) % config.checkpoint_every == 0 {
+            let path = format!("{}/epoch-{}.ot", config.checkpoint_dir, epoch_idx + 1);
+            save_checkpoint(&path, epoch_idx + 1, &bot, &opt)?;
+            if league.add_checkpoint(&path)? {
+                envs.set_opponents(&league, league_seed)?;
+            }
+        }
     }
 
-    bot.save("model.ot");
+    bot.epochs = config.epochs.max(start_epoch);
+    Ok(bot.save(&config.output)?)
 }
 
 impl Bot {
     pub fn new() -> Bot {
+        Bot::with_architecture(&Architecture::default())
+    }
+
+    pub fn with_architecture(architecture: &Architecture) -> Bot {
         let vs = nn::VarStore::new(tch::Device::Cpu);
-        let model = model(&vs.root(), &[0; 42], 7);
-        Bot { model, vs }
+        let model = model(&vs.root(), &[0; 42], 7, architecture);
+        Bot {
+            model,
+            vs,
+            architecture: architecture.clone(),
+            epochs: 0,
+            value_scale: WIN_REWARD,
+            inference: Inference::Sample { temperature: 1.0 },
+            rng: StdRng::from_entropy()
+        }
+    }
+
+    /// Reseeds the generator used to sample moves, making `predict` reproducible.
+    pub fn seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
     }
 
-    pub fn load(&mut self, path: &str) {
-        self.vs.load(path).unwrap();
+    pub fn set_inference(&mut self, inference: Inference) {
+        self.inference = inference;
     }
 
-    pub fn save(&self, path: &str) {
-        self.vs.save(path).unwrap();
+    /// Records how many epochs the weights were trained for, saved with the metadata.
+    pub fn set_epochs(&mut self, epochs: usize) {
+        self.epochs = epochs;
     }
 
-    pub fn predict(&self, board: &Board) -> i64 {
-        let obs = Tensor::from_slice(&board.flatten()).to_kind(Float);
-        let action = obs.unsqueeze(0).apply(&self.model).softmax(1, Float).multinomial(1, true);
-        let bot_move = i64::try_from(action).unwrap();
+    /// Sets the win reward the value head is trained towards, so `analyze` can
+    /// map its estimates to [-1, 1].
+    pub fn set_value_scale(&mut self, scale: f64) {
+        self.value_scale = scale;
+    }
+
+    pub fn var_store(&self) -> &nn::VarStore {
+        &self.vs
+    }
+
+    /// Raw network outputs for a batch of observations.
+    pub fn forward(&self, obs: &Tensor) -> Tensor {
+        obs.apply(&self.model)
+    }
+
+    /// Value head outputs for a batch of observations, if the network has one.
+    pub fn forward_value(&self, obs: &Tensor) -> Option<Tensor> {
+        self.model.value(obs)
+    }
+
+    /// Loads a model saved by `save`, building the network its metadata describes.
+    /// Models saved without metadata are assumed to use the default architecture.
+    pub fn open(path: &str) -> Result<Bot, ModelError> {
+        if !Path::new(path).exists() {
+            return Err(ModelError::Missing(path.to_string()))
+        }
+
+        let metadata_path = ModelMetadata::path_for(path);
+        let mut bot = if Path::new(&metadata_path).exists() {
+            Bot::with_architecture(&ModelMetadata::load(&metadata_path)?.architecture)
+        } else {
+            Bot::new()
+        };
+        bot.load(path)?;
+        Ok(bot)
+    }
+
+    /// Loads the model from a training checkpoint written by `save_checkpoint`,
+    /// ignoring the optimizer state.
+    pub fn open_checkpoint(path: &str) -> Result<Bot, ModelError> {
+        if !Path::new(path).exists() {
+            return Err(ModelError::Missing(path.to_string()))
+        }
+
+        let metadata_path = ModelMetadata::path_for(path);
+        let bot = if Path::new(&metadata_path).exists() {
+            let metadata = ModelMetadata::load(&metadata_path)?;
+            metadata.check()?;
+            Bot { value_scale: metadata.value_scale, ..Bot::with_architecture(&metadata.architecture) }
+        } else {
+            Bot::new()
+        };
+
+        let mut tensors: HashMap<String, Tensor> = Tensor::load_multi(path)?.into_iter().collect();
+        for (name, mut var) in bot.vs.variables() {
+            let value = tensors
+                .remove(&format!("model.{}", name))
+                .ok_or_else(|| TchError::TensorNameNotFound(name.clone(), path.to_string()))?;
+            tch::no_grad(|| var.f_copy_(&value))?;
+        }
+        Ok(bot)
+    }
+
+    /// Loads weights into this bot after checking the model's metadata, if any,
+    /// against its architecture, the board size and the observation encoding.
+    pub fn load(&mut self, path: &str) -> Result<(), ModelError> {
+        if !Path::new(path).exists() {
+            return Err(ModelError::Missing(path.to_string()))
+        }
+
+        let metadata_path = ModelMetadata::path_for(path);
+        if Path::new(&metadata_path).exists() {
+            let metadata = ModelMetadata::load(&metadata_path)?;
+            metadata.check()?;
+            if metadata.architecture != self.architecture {
+                return Err(ModelError::Incompatible(format!(
+                    "network is {:?}, expected {:?}",
+                    metadata.architecture, self.architecture
+                )))
+            }
+            self.epochs = metadata.epochs;
+            self.value_scale = metadata.value_scale;
+        }
+
+        self.vs.load(path)?;
+        Ok(())
+    }
+
+    /// Saves the weights to `path` and their metadata next to them.
+    pub fn save(&self, path: &str) -> Result<(), TchError> {
+        self.vs.save(path)?;
+        ModelMetadata { value_scale: self.value_scale, ..ModelMetadata::new(&self.architecture, self.epochs) }
+            .save(&ModelMetadata::path_for(path))?;
+        Ok(())
+    }
+
+    /// Writes the network in the portable format read by `Mlp::load`.
+    pub fn export(&self, path: &str) -> Result<(), TchError> {
+        let variables = self.vs.variables();
+        let mut layers = Vec::new();
+        for i in 1..=self.architecture.hidden.len() + 1 {
+            let weight = &variables[&format!("lin{}.weight", i)];
+            let (outputs, inputs) = weight.size2()?;
+            layers.push(Layer {
+                inputs: inputs as usize,
+                outputs: outputs as usize,
+                weights: Vec::<f32>::try_from(weight.view([-1]))?,
+                bias: Vec::<f32>::try_from(&variables[&format!("lin{}.bias", i)])?
+            });
+        }
+
+        Mlp { activation: self.architecture.activation, layers }.save(path)?;
+        Ok(())
+    }
+
+    /// Picks a column for `piece` on `board`. Full columns are masked out, so the
+    /// result is always a legal move as long as the board is not full.
+    pub fn predict(&mut self, board: &Board, piece: u8) -> i64 {
+        let logits = self.masked_logits(board, piece);
+
+        match self.inference {
+            Inference::Greedy => i64::try_from(logits.argmax(0, false)).unwrap(),
+            Inference::Sample { temperature } => {
+                // Sampled with the bot's own generator rather than libtorch's global one
+                // so a seeded bot replays the same moves.
+                let probs = Vec::<f64>::try_from((logits / temperature).softmax(0, Float)).unwrap();
+                WeightedIndex::new(&probs).unwrap().sample(&mut self.rng) as i64
+            }
+        }
+    }
+
+    /// The masked policy for `piece` on `board`: a softmax over the legal columns
+    /// at the bot's sampling temperature, or at temperature 1 for greedy inference.
+    /// `value` is only set for networks with a value head.
+    pub fn analyze(&self, board: &Board, piece: u8) -> Analysis {
+        let temperature = match self.inference {
+            Inference::Greedy => 1.0,
+            Inference::Sample { temperature } => temperature
+        };
+        let policy = Vec::<f64>::try_from((self.masked_logits(board, piece) / temperature).softmax(0, Float)).unwrap();
+        let value = tch::no_grad(|| self.model.value(&observation(board, piece).unsqueeze(0)))
+            .map(|value| (value.double_value(&[0]) / self.value_scale).clamp(-1.0, 1.0));
+        Analysis { policy, value }
+    }
+
+    fn masked_logits(&self, board: &Board, piece: u8) -> Tensor {
+        let obs = observation(board, piece);
+        tch::no_grad(|| {
+            mask_logits(&obs.unsqueeze(0).apply(&self.model), &legal_mask(board).unsqueeze(0)).squeeze_dim(0)
+        })
+    }
+}
+
+impl Engine for Bot {
+    fn name(&self) -> String {
+        String::from("bot")
+    }
+
+    fn choose_move(&mut self, board: &Board, piece: u8) -> usize {
+        self.predict(board, piece) as usize
+    }
 
-        bot_move
+    fn analyze(&mut self, board: &Board, piece: u8) -> Option<Analysis> {
+        Some(Bot::analyze(self, board, piece))
     }
 }
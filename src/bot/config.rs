@@ -0,0 +1,334 @@
+use crate::bot::bot::{Activation, Architecture};
+use crate::bot::env::{IllegalMoves, Rewards};
+use std::fmt;
+use std::fs;
+use std::io;
+
+/// Training algorithm selected with the `algo` key.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Algorithm {
+    /// REINFORCE on undiscounted episode returns, see `bot::train`.
+    PolicyGradient,
+    /// Deep Q-learning, see `dqn::train`.
+    Dqn,
+    /// Proximal policy optimization with a value baseline, see `ppo::train`.
+    Ppo
+}
+
+/// Hyperparameters for `train`.
+///
+/// A config file holds one `key = value` pair per line; blank lines and lines
+/// starting with `#` are ignored. The same keys can be overridden on the command
+/// line as `--key value`.
+///
+/// ```text
+/// algo = pg
+/// epochs = 1000
+/// steps_per_epoch = 5000
+/// num_envs = 16
+/// threads = 4
+/// learning_rate = 0.001
+/// hidden = 64,64
+/// activation = relu
+/// seed = 42
+/// league = random*1, heuristic*1, checkpoints*2
+/// league_checkpoints = 5
+/// first_move_rate = 0.5
+/// reward_win = 100
+/// reward_loss = -100
+/// reward_draw = 0
+/// reward_step = -1
+/// reward_illegal = -5
+/// reward_discount = 0.99
+/// reward_threat = 2
+/// illegal_moves = terminate
+/// output = model.ot
+/// checkpoint_dir = checkpoints
+/// checkpoint_every = 50
+/// metrics = metrics.csv
+/// eval_games = 100
+/// solver_depth = 4
+/// gamma = 0.99
+/// replay_capacity = 50000
+/// batch_size = 64
+/// epsilon_start = 1.0
+/// epsilon_end = 0.05
+/// epsilon_decay_steps = 100000
+/// target_update = 500
+/// double_dqn = true
+/// gae_lambda = 0.95
+/// clip_epsilon = 0.2
+/// entropy_coef = 0.01
+/// value_coef = 0.5
+/// ppo_epochs = 4
+/// minibatch_size = 256
+/// validation_split = 0.1
+/// ```
+#[derive(Clone, Debug)]
+pub struct TrainConfig {
+    pub algorithm: Algorithm,
+    pub epochs: usize,
+    pub steps_per_epoch: usize,
+    /// Environments stepped in lockstep during rollouts.
+    pub num_envs: usize,
+    /// Threads the environments are stepped on.
+    pub threads: usize,
+    pub learning_rate: f64,
+    pub architecture: Architecture,
+    pub seed: Option<u64>,
+    /// Opponent engine specs and their sampling weights, see `League`. The
+    /// `opponent` key sets a single opponent.
+    pub league: Vec<(String, f64)>,
+    /// Most recent checkpoints kept in the league.
+    pub league_checkpoints: usize,
+    /// Fraction of training episodes in which the agent plays piece 1.
+    pub first_move_rate: f64,
+    /// Set with the `reward_*` keys.
+    pub rewards: Rewards,
+    /// One of `penalize`, `forbid`, `terminate` or `resample`, see `IllegalMoves`.
+    pub illegal_moves: IllegalMoves,
+    pub output: String,
+    pub checkpoint_dir: String,
+    /// Save a checkpoint every this many epochs, 0 to disable.
+    pub checkpoint_every: usize,
+    /// CSV or JSON-lines (`.jsonl`) file receiving one line of metrics per epoch.
+    pub metrics: Option<String>,
+    /// Games played against each baseline when measuring win rates.
+    pub eval_games: usize,
+    /// Search depth of the solver baseline.
+    pub solver_depth: u32,
+    /// Discount applied to future rewards by DQN and PPO, and by their threat shaping.
+    pub gamma: f64,
+    /// Transitions kept in the DQN replay buffer.
+    pub replay_capacity: usize,
+    /// Transitions sampled per DQN update, and positions per supervised update.
+    pub batch_size: usize,
+    pub epsilon_start: f64,
+    pub epsilon_end: f64,
+    /// Environment steps over which epsilon decays linearly from start to end.
+    pub epsilon_decay_steps: usize,
+    /// Updates between copies of the online network into the target network.
+    pub target_update: usize,
+    /// Pick the bootstrap action with the online network and score it with the target network.
+    pub double_dqn: bool,
+    /// Smoothing of PPO's generalized advantage estimates, 0 for one-step TD and 1 for Monte Carlo.
+    pub gae_lambda: f64,
+    /// How far PPO lets the probability ratio move from 1 before clipping it.
+    pub clip_epsilon: f64,
+    pub entropy_coef: f64,
+    pub value_coef: f64,
+    /// Passes PPO makes over each rollout.
+    pub ppo_epochs: usize,
+    pub minibatch_size: usize,
+    /// Fraction of a supervised dataset held out for validation.
+    pub validation_split: f64
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Syntax(usize),
+    UnknownKey(String),
+    InvalidValue(String, String)
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> ConfigError {
+        ConfigError::Io(err)
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "{}", err),
+            ConfigError::Syntax(line) => write!(f, "expected `key = value` on line {}", line),
+            ConfigError::UnknownKey(key) => write!(f, "unknown option `{}`", key),
+            ConfigError::InvalidValue(key, value) => write!(f, "invalid value `{}` for `{}`", value, key)
+        }
+    }
+}
+
+impl Default for TrainConfig {
+    fn default() -> TrainConfig {
+        TrainConfig {
+            algorithm: Algorithm::PolicyGradient,
+            epochs: 1000,
+            steps_per_epoch: 5000,
+            num_envs: 16,
+            threads: 1,
+            learning_rate: 1e-3,
+            architecture: Architecture::default(),
+            seed: None,
+            league: vec![(String::from("random"), 1.0)],
+            league_checkpoints: 5,
+            first_move_rate: 0.0,
+            rewards: Rewards::default(),
+            illegal_moves: IllegalMoves::Penalize,
+            output: String::from("model.ot"),
+            checkpoint_dir: String::from("checkpoints"),
+            checkpoint_every: 0,
+            metrics: None,
+            eval_games: 100,
+            solver_depth: 4,
+            gamma: 0.99,
+            replay_capacity: 50_000,
+            batch_size: 64,
+            epsilon_start: 1.0,
+            epsilon_end: 0.05,
+            epsilon_decay_steps: 100_000,
+            target_update: 500,
+            double_dqn: false,
+            gae_lambda: 0.95,
+            clip_epsilon: 0.2,
+            entropy_coef: 0.01,
+            value_coef: 0.5,
+            ppo_epochs: 4,
+            minibatch_size: 256,
+            validation_split: 0.1
+        }
+    }
+}
+
+impl TrainConfig {
+    pub fn load(path: &str) -> Result<TrainConfig, ConfigError> {
+        let mut config = TrainConfig::default();
+        for (i, line) in fs::read_to_string(path)?.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+
+            match line.split_once('=') {
+                Some((key, value)) => config.set(key.trim(), value.trim())?,
+                None => return Err(ConfigError::Syntax(i + 1))
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        let invalid = || ConfigError::InvalidValue(key.to_string(), value.to_string());
+
+        match key {
+            "algo" => {
+                self.algorithm = match value {
+                    "pg" => Algorithm::PolicyGradient,
+                    "dqn" => Algorithm::Dqn,
+                    "ppo" => Algorithm::Ppo,
+                    _ => return Err(invalid())
+                }
+            },
+            "epochs" => self.epochs = value.parse().map_err(|_| invalid())?,
+            "steps_per_epoch" => self.steps_per_epoch = value.parse().map_err(|_| invalid())?,
+            "num_envs" => self.num_envs = value.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?,
+            "threads" => self.threads = value.parse().map_err(|_| invalid())?,
+            "learning_rate" => self.learning_rate = value.parse().map_err(|_| invalid())?,
+            "hidden" => {
+                self.architecture.hidden = value
+                    .split(',')
+                    .map(|size| size.trim().parse::<i64>())
+                    .collect::<Result<Vec<i64>, _>>()
+                    .map_err(|_| invalid())?;
+            },
+            "activation" => {
+                self.architecture.activation = match value {
+                    "tanh" => Activation::Tanh,
+                    "relu" => Activation::Relu,
+                    _ => return Err(invalid())
+                }
+            },
+            "seed" => self.seed = Some(value.parse().map_err(|_| invalid())?),
+            "opponent" => self.league = vec![(value.to_string(), 1.0)],
+            "league" => {
+                self.league = value
+                    .split(',')
+                    .map(|entry| match entry.trim().rsplit_once('*') {
+                        Some((spec, weight)) => weight.trim().parse().ok().filter(|&w: &f64| w >= 0.0).map(|w| (spec.trim().to_string(), w)),
+                        None => Some((entry.trim().to_string(), 1.0))
+                    })
+                    .collect::<Option<Vec<(String, f64)>>>()
+                    .ok_or_else(invalid)?
+            },
+            "league_checkpoints" => self.league_checkpoints = value.parse().map_err(|_| invalid())?,
+            "first_move_rate" => {
+                self.first_move_rate = value.parse().ok().filter(|r| (0.0..=1.0).contains(r)).ok_or_else(invalid)?
+            },
+            "reward_win" => self.rewards.win = value.parse().map_err(|_| invalid())?,
+            "reward_loss" => self.rewards.loss = value.parse().map_err(|_| invalid())?,
+            "reward_draw" => self.rewards.draw = value.parse().map_err(|_| invalid())?,
+            "reward_step" => self.rewards.step = value.parse().map_err(|_| invalid())?,
+            "reward_illegal" => self.rewards.illegal = value.parse().map_err(|_| invalid())?,
+            "reward_discount" => {
+                self.rewards.discount = value.parse().ok().filter(|d| (0.0..=1.0).contains(d)).ok_or_else(invalid)?
+            },
+            "reward_threat" => self.rewards.threat = value.parse().map_err(|_| invalid())?,
+            "illegal_moves" => {
+                self.illegal_moves = match value {
+                    "penalize" => IllegalMoves::Penalize,
+                    "forbid" => IllegalMoves::Forbid,
+                    "terminate" => IllegalMoves::Terminate,
+                    "resample" => IllegalMoves::Resample,
+                    _ => return Err(invalid())
+                }
+            },
+            "output" => self.output = value.to_string(),
+            "checkpoint_dir" => self.checkpoint_dir = value.to_string(),
+            "checkpoint_every" => self.checkpoint_every = value.parse().map_err(|_| invalid())?,
+            "metrics" => self.metrics = Some(value.to_string()),
+            "eval_games" => self.eval_games = value.parse().map_err(|_| invalid())?,
+            "solver_depth" => self.solver_depth = value.parse().map_err(|_| invalid())?,
+            "gamma" => self.gamma = value.parse().ok().filter(|g| (0.0..=1.0).contains(g)).ok_or_else(invalid)?,
+            "replay_capacity" => self.replay_capacity = value.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?,
+            "batch_size" => self.batch_size = value.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?,
+            "epsilon_start" => self.epsilon_start = value.parse().ok().filter(|e| (0.0..=1.0).contains(e)).ok_or_else(invalid)?,
+            "epsilon_end" => self.epsilon_end = value.parse().ok().filter(|e| (0.0..=1.0).contains(e)).ok_or_else(invalid)?,
+            "epsilon_decay_steps" => self.epsilon_decay_steps = value.parse().map_err(|_| invalid())?,
+            "target_update" => self.target_update = value.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?,
+            "double_dqn" => self.double_dqn = value.parse().map_err(|_| invalid())?,
+            "gae_lambda" => self.gae_lambda = value.parse().ok().filter(|l| (0.0..=1.0).contains(l)).ok_or_else(invalid)?,
+            "clip_epsilon" => self.clip_epsilon = value.parse().ok().filter(|&e: &f64| e > 0.0).ok_or_else(invalid)?,
+            "entropy_coef" => self.entropy_coef = value.parse().map_err(|_| invalid())?,
+            "value_coef" => self.value_coef = value.parse().map_err(|_| invalid())?,
+            "ppo_epochs" => self.ppo_epochs = value.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?,
+            "minibatch_size" => self.minibatch_size = value.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?,
+            "validation_split" => self.validation_split = value.parse().ok().filter(|f| (0.0..1.0).contains(f)).ok_or_else(invalid)?,
+            _ => return Err(ConfigError::UnknownKey(key.to_string()))
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_overrides_defaults() {
+        let mut config = TrainConfig::default();
+        assert!(config.set("epochs", "10").is_ok());
+        assert!(config.set("hidden", "64, 32").is_ok());
+        assert!(config.set("activation", "relu").is_ok());
+        assert_eq!(config.epochs, 10);
+        assert_eq!(config.architecture.hidden, vec![64, 32]);
+        assert_eq!(config.architecture.activation, Activation::Relu);
+    }
+
+    #[test]
+    fn test_set_parses_league_weights() {
+        let mut config = TrainConfig::default();
+        assert!(config.set("league", "random*1, search:4*0.5, checkpoints").is_ok());
+        assert_eq!(
+            config.league,
+            vec![(String::from("random"), 1.0), (String::from("search:4"), 0.5), (String::from("checkpoints"), 1.0)]
+        );
+        assert!(config.set("league", "random*lots").is_err());
+    }
+
+    #[test]
+    fn test_set_rejects_unknown_key_and_bad_value() {
+        let mut config = TrainConfig::default();
+        assert!(matches!(config.set("epoch", "10"), Err(ConfigError::UnknownKey(_))));
+        assert!(matches!(config.set("learning_rate", "fast"), Err(ConfigError::InvalidValue(_, _))));
+    }
+}
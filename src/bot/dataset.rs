@@ -0,0 +1,58 @@
+use crate::board::WIDTH;
+use crate::bot::env::{legal_mask, observation};
+use crate::dataset::Dataset;
+use tch::{Kind::Float, Tensor};
+
+/// Positions stacked into tensors, encoded the way `Bot::predict` sees the board.
+/// Row `i` of every tensor belongs to the same position.
+pub struct Batch {
+    pub obs: Tensor,
+    pub masks: Tensor,
+    /// Uniform distribution over each position's best moves.
+    pub targets: Tensor,
+    pub values: Tensor
+}
+
+impl Batch {
+    /// The rows at `indices`.
+    pub fn select(&self, indices: &[usize]) -> Batch {
+        let indices: Vec<i64> = indices.iter().map(|&i| i as i64).collect();
+        let indices = Tensor::from_slice(&indices);
+        Batch {
+            obs: self.obs.index_select(0, &indices),
+            masks: self.masks.index_select(0, &indices),
+            targets: self.targets.index_select(0, &indices),
+            values: self.values.index_select(0, &indices)
+        }
+    }
+}
+
+impl Dataset {
+    /// Every position as tensors. Encoding replays each game, so do it once and
+    /// take minibatches with `Batch::select`.
+    pub fn encode(&self) -> Batch {
+        let mut obs = Vec::with_capacity(self.positions.len());
+        let mut masks = Vec::with_capacity(self.positions.len());
+        let mut targets = Vec::with_capacity(self.positions.len() * WIDTH);
+        let mut values = Vec::with_capacity(self.positions.len());
+
+        for position in &self.positions {
+            let board = position.board();
+            obs.push(observation(&board, board.to_move()));
+            masks.push(legal_mask(&board));
+            let mut target = [0.0; WIDTH];
+            for &col in &position.best {
+                target[col] = 1.0 / position.best.len() as f64;
+            }
+            targets.extend_from_slice(&target);
+            values.push(position.value);
+        }
+
+        Batch {
+            obs: Tensor::stack(&obs, 0),
+            masks: Tensor::stack(&masks, 0),
+            targets: Tensor::from_slice(&targets).view([self.positions.len() as i64, WIDTH as i64]).to_kind(Float),
+            values: Tensor::from_slice(&values).to_kind(Float)
+        }
+    }
+}
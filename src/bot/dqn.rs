@@ -0,0 +1,249 @@
+use crate::bot::bot::{load_checkpoint, mask_logits, model, save_checkpoint, Bot, Inference, TrainError};
+use crate::bot::config::TrainConfig;
+use crate::bot::env::Rewards;
+use crate::bot::league::League;
+use crate::bot::metrics::{self, EpochMetrics, MetricsLog};
+use crate::bot::optim::Adam;
+use crate::bot::vec_env::VecEnv;
+use crate::engine::{RandomEngine, SearchEngine};
+use rand::{rngs::StdRng, seq::IteratorRandom, Rng, SeedableRng};
+use std::fs;
+use std::time::Instant;
+use tch::{nn, Kind::Float, Reduction, Tensor};
+
+/// One agent step kept for replay.
+struct Transition {
+    obs: Tensor,
+    action: i64,
+    reward: f64,
+    next_obs: Tensor,
+    next_mask: Tensor,
+    done: bool
+}
+
+/// Transitions stacked into tensors for one update.
+struct Batch {
+    obs: Tensor,
+    actions: Tensor,
+    rewards: Tensor,
+    next_obs: Tensor,
+    next_masks: Tensor,
+    /// 1 where the episode continues after the transition, 0 where it ended.
+    not_done: Tensor
+}
+
+/// Fixed-capacity store of past transitions, overwriting the oldest once full.
+struct ReplayBuffer {
+    capacity: usize,
+    transitions: Vec<Transition>,
+    next: usize
+}
+
+impl ReplayBuffer {
+    fn new(capacity: usize) -> ReplayBuffer {
+        ReplayBuffer { capacity, transitions: Vec::with_capacity(capacity), next: 0 }
+    }
+
+    fn len(&self) -> usize {
+        self.transitions.len()
+    }
+
+    fn push(&mut self, transition: Transition) {
+        if self.transitions.len() < self.capacity {
+            self.transitions.push(transition);
+        } else {
+            self.transitions[self.next] = transition;
+        }
+        self.next = (self.next + 1) % self.capacity;
+    }
+
+    /// Draws `n` transitions uniformly, with replacement.
+    fn sample(&self, n: usize, rng: &mut StdRng) -> Batch {
+        let picked: Vec<&Transition> = (0..n).map(|_| &self.transitions[rng.gen_range(0..self.len())]).collect();
+        let stack = |f: fn(&Transition) -> &Tensor| Tensor::stack(&picked.iter().map(|t| f(t)).collect::<Vec<_>>(), 0);
+
+        let actions: Vec<i64> = picked.iter().map(|t| t.action).collect();
+        let rewards: Vec<f64> = picked.iter().map(|t| t.reward).collect();
+        let not_done: Vec<f64> = picked.iter().map(|t| if t.done { 0.0 } else { 1.0 }).collect();
+        Batch {
+            obs: stack(|t| &t.obs),
+            actions: Tensor::from_slice(&actions).unsqueeze(1),
+            rewards: Tensor::from_slice(&rewards).to_kind(Float),
+            next_obs: stack(|t| &t.next_obs),
+            next_masks: stack(|t| &t.next_mask),
+            not_done: Tensor::from_slice(&not_done).to_kind(Float)
+        }
+    }
+}
+
+/// Trains an agent with deep Q-learning: epsilon-greedy rollouts over legal
+/// columns feed a replay buffer, and every environment step is followed by one
+/// update towards targets from a periodically synced target network.
+///
+/// The network's outputs are read as Q-values, so the saved model plays through
+/// `Bot` like a policy-gradient one, best with `Inference::Greedy`.
+pub fn train(config: &TrainConfig, resume: Option<&str>) -> Result<(), TrainError> {
+    if let Some(seed) = config.seed {
+        tch::manual_seed(seed as i64);
+    }
+    let mut rng = config.seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+
+    let mut bot = Bot::with_architecture(&config.architecture);
+    bot.set_value_scale(config.rewards.win);
+    let mut league = League::new(&config.league, config.league_checkpoints);
+    let league_seed = config.seed.unwrap_or_else(rand::random);
+    let mut envs = VecEnv::new(config.num_envs, &league, config.threads, league_seed)?;
+    envs.set_first_move_rate(config.first_move_rate);
+    envs.set_rewards(&Rewards { gamma: config.gamma, ..config.rewards.clone() });
+    envs.set_illegal_moves(config.illegal_moves);
+    let mut opt = Adam::new(bot.var_store(), config.learning_rate);
+
+    let start_epoch = match resume {
+        Some(path) => {
+            let epochs = load_checkpoint(path, &mut bot, &mut opt)?;
+            println!("Resuming from {} after {} epochs", path, epochs);
+            epochs
+        },
+        None => 0
+    };
+
+    let mut target_vs = nn::VarStore::new(tch::Device::Cpu);
+    let target = model(&target_vs.root(), &[0; 42], envs.action_space(), &config.architecture);
+    target_vs.copy(bot.var_store())?;
+
+    if config.checkpoint_every > 0 {
+        fs::create_dir_all(&config.checkpoint_dir)?;
+    }
+
+    let mut log = match &config.metrics {
+        Some(path) => Some(MetricsLog::open(path)?),
+        None => None
+    };
+    let mut random = config.seed.map_or_else(RandomEngine::new, RandomEngine::seeded);
+    let mut solver = SearchEngine::new(config.solver_depth);
+    let started = Instant::now();
+    bot.set_inference(Inference::Greedy);
+
+    let mut buffer = ReplayBuffer::new(config.replay_capacity);
+    let mut env_steps = start_epoch * config.steps_per_epoch;
+    let mut updates = 0;
+
+    for epoch_idx in start_epoch..config.epochs {
+        let seed = if epoch_idx == start_epoch { config.seed } else { None };
+        let mut obs = envs.reset(seed);
+        let mut collected = 0;
+        let mut episodes = 0;
+        let mut wins = 0;
+        let mut sum_r = 0.0;
+        let mut losses = Vec::new();
+        let mut last_q = None;
+
+        while collected < config.steps_per_epoch {
+            let progress = (env_steps as f64 / config.epsilon_decay_steps.max(1) as f64).min(1.0);
+            let epsilon = config.epsilon_start + (config.epsilon_end - config.epsilon_start) * progress;
+
+            let masks = envs.legal_masks();
+            let greedy = tch::no_grad(|| mask_logits(&bot.forward(&obs), &masks).argmax(1, false));
+            let mut actions = Vec::<i64>::try_from(greedy)?;
+            for (action, legal) in actions.iter_mut().zip(envs.legal_actions()) {
+                if rng.gen_bool(epsilon) {
+                    *action = legal.into_iter().choose(&mut rng).unwrap();
+                }
+            }
+
+            let step = envs.step(&actions);
+            for (i, &action) in actions.iter().enumerate() {
+                // Truncated episodes are treated as finished: the row already holds
+                // the next episode's first observation, so there is nothing to bootstrap from.
+                let done = step.terminated[i] || step.truncated[i];
+                buffer.push(Transition {
+                    obs: obs.get(i as i64),
+                    action,
+                    reward: step.rewards[i],
+                    next_obs: step.obs.get(i as i64),
+                    next_mask: step.masks.get(i as i64),
+                    done
+                });
+                episodes += done as i64;
+            }
+            sum_r += step.rewards.iter().sum::<f64>();
+            wins += step.infos.iter().filter(|info| info.winner == Some(info.agent_piece)).count();
+            let finished: Vec<bool> = step.terminated.iter().zip(&step.truncated).map(|(a, b)| *a || *b).collect();
+            league.record(&step.infos, &finished);
+            collected += config.num_envs;
+            env_steps += config.num_envs;
+            obs = step.obs;
+
+            if buffer.len() < config.batch_size {
+                continue
+            }
+
+            let batch = buffer.sample(config.batch_size, &mut rng);
+            let q_values = bot.forward(&batch.obs);
+            let q = q_values.gather(1, &batch.actions, false).squeeze_dim(1);
+            let next_q = tch::no_grad(|| {
+                let target_q = mask_logits(&batch.next_obs.apply(&target), &batch.next_masks);
+                if config.double_dqn {
+                    let best = mask_logits(&bot.forward(&batch.next_obs), &batch.next_masks).argmax(1, true);
+                    target_q.gather(1, &best, false).squeeze_dim(1)
+                } else {
+                    target_q.max_dim(1, false).0
+                }
+            });
+            let targets = &batch.rewards + next_q * &batch.not_done * config.gamma;
+            let loss = q.smooth_l1_loss(&targets, Reduction::Mean, 1.0);
+            opt.backward_step(&loss);
+            losses.push(f64::try_from(&loss)?);
+
+            updates += 1;
+            if updates % config.target_update == 0 {
+                target_vs.copy(bot.var_store())?;
+            }
+            last_q = Some(q_values.detach());
+        }
+
+        let loss = losses.iter().sum::<f64>() / losses.len().max(1) as f64;
+        println!(
+            "epoch: {:<3} episodes: {:<5} avg reward per episode: {:.2} wins: {:<5} loss: {:.4} buffer: {}",
+            epoch_idx,
+            episodes,
+            sum_r / episodes.max(1) as f64,
+            wins,
+            loss,
+            buffer.len()
+        );
+        println!("league win rates: {}", league.summary());
+
+        if let Some(log) = log.as_mut() {
+            // Entropy of the softmax over the last batch's Q-values, the policy `Bot` samples from.
+            let entropy = match &last_q {
+                Some(q) => {
+                    let log_probs = q.log_softmax(1, Float);
+                    f64::try_from(-(log_probs.exp() * &log_probs).sum_dim_intlist(1, false, Float).mean(Float))?
+                },
+                None => 0.0
+            };
+            log.write(&EpochMetrics {
+                epoch: epoch_idx,
+                episodes,
+                avg_reward: sum_r / episodes.max(1) as f64,
+                loss,
+                entropy,
+                win_rate_random: metrics::win_rate(&mut bot, &mut random, config.eval_games),
+                win_rate_solver: metrics::win_rate(&mut bot, &mut solver, config.eval_games),
+                wall_time: started.elapsed().as_secs_f64()
+            })?;
+        }
+
+        if config.checkpoint_every > 0 && (epoch_idx + 1) % config.checkpoint_every == 0 {
+            let path = format!("{}/epoch-{}.ot", config.checkpoint_dir, epoch_idx + 1);
+            save_checkpoint(&path, epoch_idx + 1, &bot, &opt)?;
+            if league.add_checkpoint(&path)? {
+                envs.set_opponents(&league, league_seed)?;
+            }
+        }
+    }
+
+    bot.set_epochs(config.epochs.max(start_epoch));
+    Ok(bot.save(&config.output)?)
+}
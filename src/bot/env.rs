@@ -1,80 +1,424 @@
 use crate::board::{HEIGHT, WIDTH, Board};
-use rand::{seq::IteratorRandom, rngs::ThreadRng};
+use crate::engine::Engine;
+use crate::mlp;
+use rand::{distributions::WeightedIndex, prelude::Distribution, rngs::StdRng, seq::IteratorRandom, Rng, SeedableRng};
 use tch::Tensor;
 
+/// Default reward for winning a game, and the penalty for losing one.
+pub const WIN_REWARD: f64 = 100.0;
 
+/// How `Env::step` rewards the agent.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Rewards {
+    pub win: f64,
+    pub loss: f64,
+    pub draw: f64,
+    /// Added for every legal move, usually a small penalty to favour quick wins.
+    pub step: f64,
+    pub illegal: f64,
+    /// The game outcome is scaled by `discount` to the power of the agent's moves
+    /// before the end, so earlier wins (and later losses) are worth more. 1 disables it.
+    pub discount: f64,
+    /// Weight of potential-based shaping on threats. The potential Φ is the agent's
+    /// threat count minus the opponent's, and 0 once the game is over; each step
+    /// earns `threat * (gamma * Φ(s') - Φ(s))`. 0 disables it.
+    pub threat: f64,
+    /// Discount the learner applies to future rewards. Shaping only leaves the
+    /// optimal policy unchanged when it uses the same one.
+    pub gamma: f64
+}
+
+impl Default for Rewards {
+    fn default() -> Rewards {
+        Rewards { win: WIN_REWARD, loss: -WIN_REWARD, draw: 0.0, step: -1.0, illegal: -5.0, discount: 1.0, threat: 0.0, gamma: 1.0 }
+    }
+}
+
+/// What `Env::step` does with an action for a full column.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum IllegalMoves {
+    /// Charge the illegal-move penalty and leave the state unchanged.
+    Penalize,
+    /// Treat it as a bug: agents must respect the legal mask. The episode ends at
+    /// once with `Info::illegal_action` set and only the illegal-move penalty, no
+    /// outcome, so the trainer can notice without bringing down its workers.
+    Forbid,
+    /// End the episode as a loss, on top of the illegal-move penalty.
+    Terminate,
+    /// Charge the penalty and play a random legal column instead.
+    Resample
+}
+
+/// The parts a step's reward was made of. They add up to the reward `step` returns.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RewardBreakdown {
+    pub step: f64,
+    pub illegal: f64,
+    /// Win, loss or draw reward after discounting.
+    pub outcome: f64,
+    pub shaping: f64
+}
+
+impl RewardBreakdown {
+    pub fn total(&self) -> f64 {
+        self.step + self.illegal + self.outcome + self.shaping
+    }
+}
+
+/// Opponent names and engines with their relative sampling weights.
+pub type Opponents = Vec<(String, Box<dyn Engine + Send>, f64)>;
+
+/// Agent steps after which an episode is truncated, so repeated illegal moves cannot stall a rollout.
+const MAX_EPISODE_STEPS: usize = 100;
+
+/// A reinforcement learning environment following the Gym/Gymnasium conventions.
+///
+/// An episode starts with `reset` and advances one agent action at a time with
+/// `step`. It is over once `step` reports it `terminated` (the game reached a
+/// natural end) or `truncated` (cut short by a step limit); `reset` must be
+/// called before stepping again.
+pub trait Environment {
+    type Obs;
+    type Action;
+
+    /// Starts a new episode and returns its first observation. Passing a seed
+    /// reseeds the environment's randomness so the episode can be reproduced.
+    fn reset(&mut self, seed: Option<u64>) -> (Self::Obs, Info);
+
+    /// Applies `action` and returns `(obs, reward, terminated, truncated, info)`.
+    fn step(&mut self, action: Self::Action) -> (Self::Obs, f64, bool, bool, Info);
+
+    /// Actions that are valid in the current state.
+    fn legal_actions(&self) -> Vec<Self::Action>;
+
+    fn action_space(&self) -> i64;
+
+    fn observation_space(&self) -> Vec<i64>;
+}
+
+/// Auxiliary data returned alongside every observation.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Info {
+    /// Winning piece once the game is won.
+    pub winner: Option<u8>,
+    /// Whether the agent's action was rejected as illegal.
+    pub illegal_action: bool,
+    /// Column the opponent replied with, if it moved.
+    pub opponent_action: Option<usize>,
+    /// Agent steps taken so far in the episode.
+    pub episode_steps: usize,
+    /// Piece the agent plays this episode.
+    pub agent_piece: u8,
+    /// Name of the opponent playing this episode, as given to `Env::new`.
+    pub opponent: String,
+    pub rewards: RewardBreakdown,
+    /// Columns that can be played in the returned observation.
+    pub legal_mask: [bool; WIDTH]
+}
+
+/// Connect four from the agent's perspective. By default the agent plays piece 2
+/// and the opponent opens on `reset`; the opponent also replies to every legal
+/// agent move. Observations always encode the agent's pieces as 2.
+///
+/// Each episode is played against one of several opponent engines, drawn on
+/// `reset` in proportion to their weights.
 pub struct Env {
     board: Board,
-    thread_rng: ThreadRng
+    opponents: Opponents,
+    weights: WeightedIndex<f64>,
+    /// Opponents passed to `set_opponents`, taking over on the next `reset`.
+    next_opponents: Option<Opponents>,
+    opponent: usize,
+    rng: StdRng,
+    episode_steps: usize,
+    agent: u8,
+    first_move_rate: f64,
+    rewards: Rewards,
+    illegal_moves: IllegalMoves
 }
 
 pub struct Step<A> {
     pub obs: Tensor,
+    pub mask: Tensor,
     pub action: A,
     pub reward: f64,
     pub is_done: bool,
 }
 
 impl Env {
-    pub fn new() -> Env {
+    /// Creates an environment playing against `opponents`, given as names, engines
+    /// and their relative weights. Panics if the weights are not positive.
+    pub fn new(opponents: Opponents) -> Env {
         Env {
             board: Board::new(),
-            thread_rng: rand::thread_rng()
+            weights: opponent_weights(&opponents),
+            opponents,
+            next_opponents: None,
+            opponent: 0,
+            rng: StdRng::from_entropy(),
+            episode_steps: 0,
+            agent: 2,
+            first_move_rate: 0.0,
+            rewards: Rewards::default(),
+            illegal_moves: IllegalMoves::Penalize
+        }
+    }
+
+    /// Replaces the opponents from the next `reset` on. The episode in progress
+    /// is finished against its current opponent.
+    pub fn set_opponents(&mut self, opponents: Opponents) {
+        self.next_opponents = Some(opponents);
+    }
+
+    pub fn set_rewards(&mut self, rewards: Rewards) {
+        self.rewards = rewards;
+    }
+
+    pub fn set_illegal_moves(&mut self, illegal_moves: IllegalMoves) {
+        self.illegal_moves = illegal_moves;
+    }
+
+    /// Fraction of episodes in which the agent plays piece 1 and moves first.
+    pub fn set_first_move_rate(&mut self, rate: f64) {
+        self.first_move_rate = rate;
+    }
+
+    pub fn legal_mask(&self) -> Tensor {
+        legal_mask(&self.board)
+    }
+
+    fn play_opponent_move(&mut self) -> usize {
+        let piece = self.agent ^ 3;
+        let col = self.opponents[self.opponent].1.choose_move(&self.board, piece);
+        let _ = self.board.place(col, piece);
+        col
+    }
+
+    /// Shaping potential: the agent's threats minus the opponent's.
+    fn threat_potential(&self) -> f64 {
+        self.board.threats(self.agent).len() as f64 - self.board.threats(self.agent ^ 3).len() as f64
+    }
+
+    fn to_tensor(&self) -> Tensor {
+        observation(&self.board, self.agent)
+    }
+
+    fn info(&self) -> Info {
+        Info {
+            winner: self.board.winner(),
+            episode_steps: self.episode_steps,
+            agent_piece: self.agent,
+            opponent: self.opponents[self.opponent].0.clone(),
+            legal_mask: legal_columns(&self.board),
+            ..Default::default()
         }
     }
+}
 
-    pub fn reset(&mut self) -> Tensor {
+impl Environment for Env {
+    type Obs = Tensor;
+    type Action = i64;
+
+    fn reset(&mut self, seed: Option<u64>) -> (Tensor, Info) {
+        if let Some(seed) = seed {
+            self.rng = StdRng::seed_from_u64(seed);
+        }
+        if let Some(opponents) = self.next_opponents.take() {
+            self.weights = opponent_weights(&opponents);
+            self.opponents = opponents;
+        }
         self.board = Board::new();
-        self.play_random_move();
-        self.to_tensor()
+        self.episode_steps = 0;
+        self.agent = if self.rng.gen_bool(self.first_move_rate) { 1 } else { 2 };
+        self.opponent = self.weights.sample(&mut self.rng);
+
+        let opponent_action = if self.agent == 2 { Some(self.play_opponent_move()) } else { None };
+        (self.to_tensor(), Info { opponent_action, ..self.info() })
     }
 
-    pub fn step(&mut self, action: i64) -> Step<i64> {
-        let mut is_done = false;
-        let mut reward = 0.0;
-        let placement = self.board.place(action as usize, 2);
+    fn step(&mut self, action: i64) -> (Tensor, f64, bool, bool, Info) {
+        self.episode_steps += 1;
+        let mut rewards = RewardBreakdown::default();
+        let mut illegal_action = false;
+        let mut opponent_action = None;
+        let mut forfeited = false;
+        let mut aborted = false;
+        let shaping = self.rewards.threat != 0.0;
+        let potential = if shaping { self.threat_potential() } else { 0.0 };
+
+        let mut placement = self.board.place(action as usize, self.agent);
+        if placement.is_err() {
+            rewards.illegal = self.rewards.illegal;
+            illegal_action = true;
+            match self.illegal_moves {
+                IllegalMoves::Penalize => {},
+                IllegalMoves::Forbid => aborted = true,
+                IllegalMoves::Terminate => forfeited = true,
+                IllegalMoves::Resample => {
+                    let col = self.board.available_columns().into_iter().choose(&mut self.rng).unwrap();
+                    placement = self.board.place(col, self.agent);
+                }
+            }
+        }
         if placement.is_ok() {
-            reward -= 1.0;
-            if self.board.finished() {
-                is_done = true;
-            } else {
-                self.play_random_move();
+            rewards.step = self.rewards.step;
+            if !self.board.finished() {
+                opponent_action = Some(self.play_opponent_move());
             }
-        } else {
-            reward -= 5.0;
         }
 
-        if let Some(winner) = self.board.winner() {
-            if winner == 1 {
-                reward -= 100.0;
-            } else if winner == 2 {
-                reward += 100.0;
-            }
+        let terminated = self.board.finished() || forfeited || aborted;
+        if self.board.finished() || forfeited {
+            let outcome = match self.board.winner() {
+                Some(winner) if winner == self.agent => self.rewards.win,
+                Some(_) => self.rewards.loss,
+                None if forfeited => self.rewards.loss,
+                None => self.rewards.draw
+            };
+            rewards.outcome = outcome * self.rewards.discount.powi(self.episode_steps as i32 - 1);
+        }
+        if shaping {
+            let next_potential = if terminated { 0.0 } else { self.threat_potential() };
+            rewards.shaping = self.rewards.threat * (self.rewards.gamma * next_potential - potential);
         }
 
-        Step { obs: self.to_tensor(), action, reward: reward, is_done }
+        let truncated = !terminated && self.episode_steps >= MAX_EPISODE_STEPS;
+        let reward = rewards.total();
+        let info = Info { illegal_action, opponent_action, rewards, ..self.info() };
+
+        (self.to_tensor(), reward, terminated, truncated, info)
+    }
+
+    fn legal_actions(&self) -> Vec<i64> {
+        self.board.available_columns().into_iter().map(|col| col as i64).collect()
     }
 
-    pub fn action_space(&self) -> i64 {
+    fn action_space(&self) -> i64 {
         WIDTH as i64
     }
 
-    pub fn observation_space(&self) -> Vec<i64> {
+    fn observation_space(&self) -> Vec<i64> {
         (0..(WIDTH * HEIGHT)).map(|i| i as i64).collect::<Vec<i64>>()
     }
+}
 
-    fn play_random_move(&mut self) {
-        let cols = self.board.available_columns();
-        let _ = self.board.place(cols.iter().choose(&mut self.thread_rng).unwrap().to_owned(), 1);
-    }
+/// Sampling distribution over `opponents`. Panics if the weights are not positive.
+fn opponent_weights(opponents: &Opponents) -> WeightedIndex<f64> {
+    WeightedIndex::new(opponents.iter().map(|(_, _, weight)| *weight)).unwrap()
+}
 
-    fn to_tensor(&self) -> Tensor {
-        Tensor::from_slice(&self.board.flatten()).to_kind(tch::Kind::Float)
+/// `mlp::encode` as a float tensor.
+pub fn observation(board: &Board, piece: u8) -> Tensor {
+    Tensor::from_slice(&mlp::encode(board, piece))
+}
+
+/// Boolean tensor over the action space, true for columns that can still be played.
+pub fn legal_mask(board: &Board) -> Tensor {
+    Tensor::from_slice(&legal_columns(board))
+}
+
+/// True for columns that can still be played.
+pub fn legal_columns(board: &Board) -> [bool; WIDTH] {
+    let mut mask = [false; WIDTH];
+    for col in board.available_columns() {
+        mask[col] = true;
     }
+    mask
 }
 
-impl Step<i64> {
-    pub fn copy_with_obs(&self, obs: &Tensor) -> Step<i64> {
-        Step { obs: obs.copy(), action: self.action, reward: self.reward, is_done: self.is_done }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Column;
+
+    /// An environment where the agent moves first against an opponent stuck on `col`.
+    fn env_against(col: usize) -> Env {
+        let mut env = Env::new(vec![(String::from("column"), Box::new(Column(col)), 1.0)]);
+        env.set_first_move_rate(1.0);
+        env.reset(Some(0));
+        env
+    }
+
+    #[test]
+    fn test_win_terminates_the_episode() {
+        let mut env = env_against(6);
+        for _ in 0..3 {
+            let (_, _, terminated, truncated, info) = env.step(0);
+            assert!(!terminated && !truncated);
+            assert_eq!(info.winner, None);
+        }
+
+        let (_, reward, terminated, truncated, info) = env.step(0);
+        assert!(terminated && !truncated);
+        assert_eq!(info.winner, Some(1));
+        assert_eq!(info.opponent_action, None);
+        assert_eq!(reward, WIN_REWARD - 1.0);
+    }
+
+    #[test]
+    fn test_threat_shaping_cancels_out_over_an_episode() {
+        let mut env = env_against(6);
+        env.set_rewards(Rewards { threat: 1.0, ..Rewards::default() });
+
+        let shaping: Vec<f64> = [3, 2, 1, 0].iter().map(|&col| env.step(col).4.rewards.shaping).collect();
+        // Three in a row threatens both ends while the opponent's column threatens once.
+        assert_eq!(shaping, vec![0.0, 0.0, 1.0, -1.0]);
+    }
+
+    #[test]
+    fn test_repeated_illegal_moves_truncate_the_episode() {
+        // Both sides fill column 0 without a winner, then the agent keeps playing it.
+        let mut env = env_against(0);
+        for step in 1..MAX_EPISODE_STEPS {
+            let (_, _, terminated, truncated, info) = env.step(0);
+            assert!(!terminated && !truncated);
+            assert_eq!(info.illegal_action, step > 3);
+        }
+
+        let (_, reward, terminated, truncated, info) = env.step(0);
+        assert!(!terminated && truncated);
+        assert_eq!(info.episode_steps, MAX_EPISODE_STEPS);
+        assert_eq!(reward, Rewards::default().illegal);
+    }
+
+    #[test]
+    fn test_terminate_ends_the_episode_as_a_loss() {
+        let mut env = env_against(0);
+        env.set_illegal_moves(IllegalMoves::Terminate);
+        for _ in 0..3 {
+            env.step(0);
+        }
+
+        let (_, reward, terminated, truncated, info) = env.step(0);
+        assert!(terminated && !truncated);
+        assert!(info.illegal_action);
+        assert_eq!(info.winner, None);
+        assert_eq!(reward, Rewards::default().illegal - WIN_REWARD);
+    }
+
+    #[test]
+    fn test_new_opponents_wait_for_the_next_episode() {
+        let mut env = env_against(6);
+        env.step(0);
+        env.set_opponents(vec![(String::from("column 5"), Box::new(Column(5)), 1.0)]);
+
+        let (_, _, _, _, info) = env.step(0);
+        assert_eq!(info.opponent, "column");
+        assert_eq!(info.opponent_action, Some(6));
+
+        let (_, info) = env.reset(None);
+        assert_eq!(info.opponent, "column 5");
+    }
+
+    #[test]
+    fn test_info_reports_the_legal_mask() {
+        let mut env = env_against(0);
+        for _ in 0..3 {
+            let (_, _, _, _, info) = env.step(0);
+            assert_eq!(info.legal_mask, legal_columns(&env.board));
+        }
+
+        let (_, _, _, _, info) = env.step(1);
+        assert_eq!(info.legal_mask, [false, true, true, true, true, true, true]);
     }
 }
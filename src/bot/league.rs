@@ -0,0 +1,170 @@
+use crate::board::Board;
+use crate::bot::env::{Info, Opponents};
+use crate::engine::{self, Engine, SpecError};
+use rand::{distributions::WeightedIndex, prelude::Distribution, rngs::StdRng, SeedableRng};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// League entry standing for the checkpoints saved so far in the current run.
+pub const CHECKPOINTS: &str = "checkpoints";
+
+/// The opponents an agent trains against, drawn per episode in proportion to
+/// their weights.
+///
+/// Entries are engine specs (see `engine::from_spec`) such as `random`,
+/// `heuristic` or `model:old.ot`, plus `checkpoints`, whose weight is shared by
+/// the most recent checkpoints of the run. Until a checkpoint exists that
+/// entry is skipped, and a league with nothing else plays random moves.
+/// Checkpoints are loaded once and shared by every environment.
+pub struct League {
+    entries: Vec<(String, f64)>,
+    /// Names and engines of the kept checkpoints, oldest first.
+    checkpoints: Vec<(String, SharedEngine)>,
+    max_checkpoints: usize,
+    /// Games and wins of the agent against each opponent, by name.
+    results: BTreeMap<String, (usize, usize)>
+}
+
+type SharedEngine = Arc<Mutex<Box<dyn Engine + Send>>>;
+
+/// One environment's handle on a shared checkpoint. Moves are sampled from the
+/// checkpoint's policy with the handle's own generator, so seeded runs don't
+/// depend on the order threads reach the shared engine.
+struct Checkpoint {
+    engine: SharedEngine,
+    rng: StdRng
+}
+
+impl Engine for Checkpoint {
+    fn name(&self) -> String {
+        self.engine.lock().unwrap().name()
+    }
+
+    fn choose_move(&mut self, board: &Board, piece: u8) -> usize {
+        let mut engine = self.engine.lock().unwrap();
+        match engine.analyze(board, piece) {
+            Some(analysis) => WeightedIndex::new(&analysis.policy).unwrap().sample(&mut self.rng),
+            None => engine.choose_move(board, piece)
+        }
+    }
+}
+
+impl League {
+    pub fn new(entries: &[(String, f64)], max_checkpoints: usize) -> League {
+        League { entries: entries.to_vec(), checkpoints: Vec::new(), max_checkpoints, results: BTreeMap::new() }
+    }
+
+    /// Opponent specs and weights with the checkpoint entry expanded. These are
+    /// the names reported in `Info::opponent`.
+    pub fn opponents(&self) -> Vec<(String, f64)> {
+        let mut opponents = Vec::new();
+        for (spec, weight) in &self.entries {
+            if spec == CHECKPOINTS {
+                for (name, _) in &self.checkpoints {
+                    opponents.push((name.clone(), weight / self.checkpoints.len() as f64));
+                }
+            } else {
+                opponents.push((spec.clone(), *weight));
+            }
+        }
+
+        opponents.retain(|(_, weight)| *weight > 0.0);
+        if opponents.is_empty() {
+            opponents.push((String::from("random"), 1.0));
+        }
+        opponents
+    }
+
+    /// Builds one engine per opponent, seeded from `seed`. Checkpoints come from
+    /// the shared copies loaded by `add_checkpoint`.
+    pub fn engines(&self, seed: u64) -> Result<Opponents, SpecError> {
+        self.opponents()
+            .into_iter()
+            .enumerate()
+            .map(|(i, (spec, weight))| {
+                let seed = seed.wrapping_add(i as u64);
+                let engine: Box<dyn Engine + Send> = match self.checkpoints.iter().find(|(name, _)| *name == spec) {
+                    Some((_, engine)) => Box::new(Checkpoint { engine: engine.clone(), rng: StdRng::seed_from_u64(seed) }),
+                    None => engine::from_spec(&spec, seed)?
+                };
+                Ok((spec, engine, weight))
+            })
+            .collect()
+    }
+
+    /// Loads the checkpoint saved at `path` and adds it, dropping the oldest beyond
+    /// the limit. Returns whether the league plays checkpoints, in which case
+    /// environments need their opponents rebuilt.
+    pub fn add_checkpoint(&mut self, path: &str) -> Result<bool, SpecError> {
+        if !self.plays_checkpoints() {
+            return Ok(false)
+        }
+
+        let name = format!("checkpoint:{}", path);
+        let engine = engine::from_spec(&name, 0)?;
+        self.push_checkpoint(name, engine);
+        Ok(true)
+    }
+
+    fn plays_checkpoints(&self) -> bool {
+        self.entries.iter().any(|(spec, _)| spec == CHECKPOINTS) && self.max_checkpoints > 0
+    }
+
+    fn push_checkpoint(&mut self, name: String, engine: Box<dyn Engine + Send>) {
+        self.checkpoints.push((name, Arc::new(Mutex::new(engine))));
+        if self.checkpoints.len() > self.max_checkpoints {
+            self.checkpoints.remove(0);
+        }
+    }
+
+    /// Counts the outcome of every finished episode in a vectorised step.
+    pub fn record(&mut self, infos: &[Info], dones: &[bool]) {
+        for (info, _) in infos.iter().zip(dones).filter(|(_, &done)| done) {
+            let entry = self.results.entry(info.opponent.clone()).or_insert((0, 0));
+            entry.0 += 1;
+            entry.1 += (info.winner == Some(info.agent_piece)) as usize;
+        }
+    }
+
+    /// Win rate against every opponent played so far, e.g. `random 0.92 (210), heuristic 0.31 (95)`.
+    pub fn summary(&self) -> String {
+        let rates: Vec<String> = self
+            .results
+            .iter()
+            .map(|(name, (games, wins))| format!("{} {:.2} ({})", name, *wins as f64 / *games as f64, games))
+            .collect();
+        rates.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::RandomEngine;
+
+    #[test]
+    fn test_checkpoints_share_their_weight() {
+        let mut league = League::new(&[(String::from("random"), 1.0), (String::from(CHECKPOINTS), 2.0)], 2);
+        assert_eq!(league.opponents(), vec![(String::from("random"), 1.0)]);
+
+        assert!(league.plays_checkpoints());
+        for path in ["a.ot", "b.ot", "c.ot"] {
+            league.push_checkpoint(format!("checkpoint:{}", path), Box::new(RandomEngine::seeded(0)));
+        }
+        let opponents = league.opponents();
+        assert_eq!(opponents.len(), 3);
+        assert_eq!(opponents[1], (String::from("checkpoint:b.ot"), 1.0));
+        assert_eq!(opponents[2], (String::from("checkpoint:c.ot"), 1.0));
+    }
+
+    #[test]
+    fn test_record_counts_finished_episodes_per_opponent() {
+        let mut league = League::new(&[(String::from("random"), 1.0), (String::from("greedy"), 1.0)], 0);
+        let greedy = || String::from("greedy");
+        let win = Info { winner: Some(2), agent_piece: 2, opponent: greedy(), ..Default::default() };
+        let loss = Info { winner: Some(1), agent_piece: 2, opponent: greedy(), ..Default::default() };
+        let running = Info { opponent: String::from("random"), ..Default::default() };
+        league.record(&[win, loss, running], &[true, true, false]);
+        assert_eq!(league.summary(), "greedy 0.50 (2)");
+    }
+}
@@ -0,0 +1,212 @@
+use crate::board::{HEIGHT, WIDTH};
+use crate::bot::bot::{Activation, Architecture};
+use crate::bot::env::WIN_REWARD;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tch::TchError;
+
+/// Version of the observation encoding in `mlp::encode`. Bump it whenever the
+/// encoding changes so older models are rejected instead of playing nonsense.
+pub const ENCODING_VERSION: u32 = 1;
+
+/// Describes the network saved in a model file. It is written next to the
+/// weights as `<model>.meta`, one `key = value` pair per line:
+///
+/// ```text
+/// hidden = 32
+/// activation = tanh
+/// value_head = false
+/// board = 7x6
+/// encoding = 1
+/// epochs = 1000
+/// saved_at = 1760000000
+/// value_scale = 100
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModelMetadata {
+    pub architecture: Architecture,
+    pub width: usize,
+    pub height: usize,
+    pub encoding: u32,
+    /// Training epochs completed when the model was saved.
+    pub epochs: usize,
+    /// Seconds since the Unix epoch.
+    pub saved_at: u64,
+    /// Value head output for a won position, the win reward the model was
+    /// trained with. Models saved without it assume `WIN_REWARD`.
+    pub value_scale: f64
+}
+
+#[derive(Debug)]
+pub enum ModelError {
+    /// No model file at the given path.
+    Missing(String),
+    Io(io::Error),
+    /// The metadata file could not be parsed.
+    Malformed(String),
+    /// The model was saved for a different network, board or encoding.
+    Incompatible(String),
+    Tch(TchError)
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ModelError::Missing(path) => write!(f, "no model found at {}", path),
+            ModelError::Io(err) => write!(f, "{}", err),
+            ModelError::Malformed(msg) => write!(f, "malformed model metadata: {}", msg),
+            ModelError::Incompatible(msg) => write!(f, "incompatible model: {}", msg),
+            ModelError::Tch(err) => write!(f, "{}", err)
+        }
+    }
+}
+
+impl From<io::Error> for ModelError {
+    fn from(err: io::Error) -> ModelError {
+        ModelError::Io(err)
+    }
+}
+
+impl From<TchError> for ModelError {
+    fn from(err: TchError) -> ModelError {
+        ModelError::Tch(err)
+    }
+}
+
+impl ModelMetadata {
+    /// Metadata for a model of `architecture` trained for `epochs`, saved now.
+    pub fn new(architecture: &Architecture, epochs: usize) -> ModelMetadata {
+        ModelMetadata {
+            architecture: architecture.clone(),
+            width: WIDTH,
+            height: HEIGHT,
+            encoding: ENCODING_VERSION,
+            epochs,
+            saved_at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+            value_scale: WIN_REWARD
+        }
+    }
+
+    /// Where the metadata for the model at `model` lives.
+    pub fn path_for(model: &str) -> String {
+        format!("{}.meta", model)
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let hidden: Vec<String> = self.architecture.hidden.iter().map(|size| size.to_string()).collect();
+        let activation = match self.architecture.activation {
+            Activation::Tanh => "tanh",
+            Activation::Relu => "relu"
+        };
+        fs::write(
+            path,
+            format!(
+                "hidden = {}\nactivation = {}\nvalue_head = {}\nboard = {}x{}\nencoding = {}\nepochs = {}\nsaved_at = {}\nvalue_scale = {}\n",
+                hidden.join(","),
+                activation,
+                self.architecture.value_head,
+                self.width,
+                self.height,
+                self.encoding,
+                self.epochs,
+                self.saved_at,
+                self.value_scale
+            )
+        )
+    }
+
+    pub fn load(path: &str) -> Result<ModelMetadata, ModelError> {
+        let mut metadata = ModelMetadata::new(&Architecture::default(), 0);
+        let mut seen = 0;
+
+        for line in fs::read_to_string(path)?.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+
+            let (key, value) = line.split_once('=').ok_or_else(|| ModelError::Malformed(line.to_string()))?;
+            let (key, value) = (key.trim(), value.trim());
+            let invalid = || ModelError::Malformed(format!("invalid value `{}` for `{}`", value, key));
+
+            match key {
+                "hidden" => {
+                    metadata.architecture.hidden = value
+                        .split(',')
+                        .map(|size| size.trim().parse::<i64>())
+                        .collect::<Result<Vec<i64>, _>>()
+                        .map_err(|_| invalid())?
+                },
+                "activation" => {
+                    metadata.architecture.activation = match value {
+                        "tanh" => Activation::Tanh,
+                        "relu" => Activation::Relu,
+                        _ => return Err(invalid())
+                    }
+                },
+                "value_head" => metadata.architecture.value_head = value.parse().map_err(|_| invalid())?,
+                "board" => {
+                    let (width, height) = value.split_once('x').ok_or_else(invalid)?;
+                    metadata.width = width.parse().map_err(|_| invalid())?;
+                    metadata.height = height.parse().map_err(|_| invalid())?;
+                },
+                "encoding" => metadata.encoding = value.parse().map_err(|_| invalid())?,
+                "epochs" => metadata.epochs = value.parse().map_err(|_| invalid())?,
+                "saved_at" => metadata.saved_at = value.parse().map_err(|_| invalid())?,
+                "value_scale" => metadata.value_scale = value.parse().map_err(|_| invalid())?,
+                _ => return Err(ModelError::Malformed(format!("unknown key `{}`", key)))
+            }
+            seen += 1;
+        }
+
+        if seen == 0 {
+            return Err(ModelError::Malformed(format!("{} is empty", path)))
+        }
+        Ok(metadata)
+    }
+
+    /// Checks that the model fits this build's board and encoding.
+    pub fn check(&self) -> Result<(), ModelError> {
+        if (self.width, self.height) != (WIDTH, HEIGHT) {
+            return Err(ModelError::Incompatible(format!(
+                "trained on a {}x{} board, expected {}x{}",
+                self.width, self.height, WIDTH, HEIGHT
+            )))
+        }
+        if self.encoding != ENCODING_VERSION {
+            return Err(ModelError::Incompatible(format!(
+                "uses observation encoding {}, expected {}",
+                self.encoding, ENCODING_VERSION
+            )))
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let architecture = Architecture { hidden: vec![64, 32], activation: Activation::Relu, value_head: true };
+        let metadata = ModelMetadata { value_scale: 50.0, ..ModelMetadata::new(&architecture, 12) };
+        let path = std::env::temp_dir().join("connect_four_metadata_test.meta");
+        let path = path.to_str().unwrap();
+        assert!(metadata.save(path).is_ok());
+
+        let loaded = ModelMetadata::load(path).unwrap();
+        let _ = fs::remove_file(path);
+        assert_eq!(loaded, metadata);
+        assert!(loaded.check().is_ok());
+    }
+
+    #[test]
+    fn test_check_rejects_other_encoding() {
+        let mut metadata = ModelMetadata::new(&Architecture::default(), 0);
+        metadata.encoding = ENCODING_VERSION + 1;
+        assert!(matches!(metadata.check(), Err(ModelError::Incompatible(_))));
+    }
+}
@@ -0,0 +1,78 @@
+use crate::arena::{self, Openings};
+use crate::engine::Engine;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+
+/// Statistics recorded at the end of every training epoch.
+#[derive(Clone, Debug, Default)]
+pub struct EpochMetrics {
+    pub epoch: usize,
+    pub episodes: i64,
+    pub avg_reward: f64,
+    pub loss: f64,
+    pub entropy: f64,
+    pub win_rate_random: f64,
+    pub win_rate_solver: f64,
+    /// Seconds since training started.
+    pub wall_time: f64
+}
+
+const CSV_HEADER: &str = "epoch,episodes,avg_reward,loss,entropy,win_rate_random,win_rate_solver,wall_time";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MetricsFormat {
+    Csv,
+    Jsonl
+}
+
+/// Appends one line per epoch to a CSV or JSON-lines file, chosen by the file extension.
+pub struct MetricsLog {
+    file: File,
+    format: MetricsFormat
+}
+
+impl MetricsLog {
+    /// Opens `path` for appending so a resumed run continues the same log.
+    pub fn open(path: &str) -> io::Result<MetricsLog> {
+        let format = if path.ends_with(".jsonl") || path.ends_with(".json") {
+            MetricsFormat::Jsonl
+        } else {
+            MetricsFormat::Csv
+        };
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        if format == MetricsFormat::Csv && file.metadata()?.len() == 0 {
+            writeln!(file, "{}", CSV_HEADER)?;
+        }
+
+        Ok(MetricsLog { file, format })
+    }
+
+    pub fn write(&mut self, m: &EpochMetrics) -> io::Result<()> {
+        match self.format {
+            MetricsFormat::Csv => writeln!(
+                self.file,
+                "{},{},{},{},{},{},{},{:.3}",
+                m.epoch, m.episodes, m.avg_reward, m.loss, m.entropy, m.win_rate_random, m.win_rate_solver, m.wall_time
+            ),
+            MetricsFormat::Jsonl => writeln!(
+                self.file,
+                "{{\"epoch\":{},\"episodes\":{},\"avg_reward\":{},\"loss\":{},\"entropy\":{},\"win_rate_random\":{},\"win_rate_solver\":{},\"wall_time\":{:.3}}}",
+                m.epoch, m.episodes, m.avg_reward, m.loss, m.entropy, m.win_rate_random, m.win_rate_solver, m.wall_time
+            )
+        }?;
+        self.file.flush()
+    }
+}
+
+/// Random plies played before the engines take over. Both sides in the
+/// evaluation are deterministic, so without them every game would be the same.
+const OPENING_PLIES: usize = 2;
+
+/// Fraction of `games` won by `engine` playing second against `opponent`,
+/// each game starting from a random opening of `OPENING_PLIES` moves. The
+/// openings come from a fixed seed so epochs are compared on the same games.
+pub fn win_rate(engine: &mut dyn Engine, opponent: &mut dyn Engine, games: usize) -> f64 {
+    let record = arena::play_match(engine, opponent, 2, games, &mut Openings::new(OPENING_PLIES, 0));
+    record.rate(record.wins)
+}
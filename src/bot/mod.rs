@@ -1,3 +1,13 @@
 pub mod bot;
+pub mod config;
+pub mod dataset;
+pub mod dqn;
+pub mod metadata;
+pub mod ppo;
+pub mod supervised;
 
 mod env;
+mod league;
+mod metrics;
+mod optim;
+mod vec_env;
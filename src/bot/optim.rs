@@ -0,0 +1,81 @@
+use tch::{nn::VarStore, TchError, Tensor};
+
+/// Adam optimizer whose moment estimates can be saved and restored.
+///
+/// `tch::nn::Optimizer` keeps its state inside libtorch with no way to read it
+/// back, which makes resuming a run from a checkpoint restart the moment
+/// estimates from zero.
+pub struct Adam {
+    lr: f64,
+    beta1: f64,
+    beta2: f64,
+    eps: f64,
+    step: i64,
+    vars: Vec<(String, Tensor)>,
+    m: Vec<Tensor>,
+    v: Vec<Tensor>
+}
+
+impl Adam {
+    pub fn new(vs: &VarStore, lr: f64) -> Adam {
+        let mut vars: Vec<(String, Tensor)> = vs.variables().into_iter().collect();
+        vars.sort_by(|a, b| a.0.cmp(&b.0));
+        let m = vars.iter().map(|(_, var)| var.zeros_like()).collect();
+        let v = vars.iter().map(|(_, var)| var.zeros_like()).collect();
+
+        Adam { lr, beta1: 0.9, beta2: 0.999, eps: 1e-8, step: 0, vars, m, v }
+    }
+
+    pub fn backward_step(&mut self, loss: &Tensor) {
+        for (_, var) in self.vars.iter_mut() {
+            var.zero_grad();
+        }
+        loss.backward();
+
+        self.step += 1;
+        let bias1 = 1.0 - self.beta1.powi(self.step as i32);
+        let bias2 = 1.0 - self.beta2.powi(self.step as i32);
+
+        tch::no_grad(|| {
+            for (i, (_, var)) in self.vars.iter_mut().enumerate() {
+                let grad = var.grad();
+                if !grad.defined() {
+                    continue
+                }
+
+                self.m[i] = &self.m[i] * self.beta1 + &grad * (1.0 - self.beta1);
+                self.v[i] = &self.v[i] * self.beta2 + &grad * &grad * (1.0 - self.beta2);
+                let update = (&self.m[i] / bias1) / ((&self.v[i] / bias2).sqrt() + self.eps) * self.lr;
+                *var -= update;
+            }
+        });
+    }
+
+    /// Named tensors describing the optimizer state, suitable for `Tensor::save_multi`.
+    pub fn state(&self) -> Vec<(String, Tensor)> {
+        let mut state = vec![(String::from("step"), Tensor::from_slice(&[self.step]))];
+        for (i, (name, _)) in self.vars.iter().enumerate() {
+            state.push((format!("m.{}", name), self.m[i].shallow_clone()));
+            state.push((format!("v.{}", name), self.v[i].shallow_clone()));
+        }
+        state
+    }
+
+    /// Restores state previously returned by `state`.
+    pub fn load_state(&mut self, state: &[(String, Tensor)]) -> Result<(), TchError> {
+        let find = |name: &str| {
+            state
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, t)| t)
+                .ok_or_else(|| TchError::TensorNameNotFound(name.to_string(), String::from("optimizer state")))
+        };
+
+        self.step = find("step")?.int64_value(&[0]);
+        for (i, (name, _)) in self.vars.iter().enumerate() {
+            self.m[i] = find(&format!("m.{}", name))?.copy();
+            self.v[i] = find(&format!("v.{}", name))?.copy();
+        }
+        Ok(())
+    }
+}
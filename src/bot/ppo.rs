@@ -0,0 +1,223 @@
+use crate::bot::bot::{load_checkpoint, mask_logits, save_checkpoint, Architecture, Bot, Inference, TrainError};
+use crate::bot::config::TrainConfig;
+use crate::bot::env::Rewards;
+use crate::bot::league::League;
+use crate::bot::metrics::{self, EpochMetrics, MetricsLog};
+use crate::bot::optim::Adam;
+use crate::bot::vec_env::VecEnv;
+use crate::engine::{RandomEngine, SearchEngine};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use std::fs;
+use std::time::Instant;
+use tch::{Kind::Float, Reduction, Tensor};
+
+/// Generalized advantage estimates for one environment's rollout. `values` has
+/// one more entry than `rewards`: the estimate for the state after the last step.
+/// A step with `dones` set ends its episode, so nothing is bootstrapped across it.
+fn advantages(rewards: &[f64], values: &[f64], dones: &[bool], gamma: f64, lambda: f64) -> Vec<f64> {
+    let mut advantages = vec![0.0; rewards.len()];
+    let mut next = 0.0;
+    for t in (0..rewards.len()).rev() {
+        let continues = if dones[t] { 0.0 } else { 1.0 };
+        let delta = rewards[t] + gamma * values[t + 1] * continues - values[t];
+        next = delta + gamma * lambda * continues * next;
+        advantages[t] = next;
+    }
+    advantages
+}
+
+/// Trains an actor-critic agent with proximal policy optimization.
+///
+/// Each epoch collects `steps_per_epoch` steps, estimates advantages with GAE
+/// from the value head, then makes `ppo_epochs` passes over the rollout in
+/// shuffled minibatches minimising the clipped surrogate loss plus weighted
+/// value and entropy terms. The policy and value heads share the hidden layers.
+pub fn train(config: &TrainConfig, resume: Option<&str>) -> Result<(), TrainError> {
+    if let Some(seed) = config.seed {
+        tch::manual_seed(seed as i64);
+    }
+    let mut rng = config.seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+
+    let architecture = Architecture { value_head: true, ..config.architecture.clone() };
+    let mut bot = Bot::with_architecture(&architecture);
+    bot.set_value_scale(config.rewards.win);
+    let mut league = League::new(&config.league, config.league_checkpoints);
+    let league_seed = config.seed.unwrap_or_else(rand::random);
+    let mut envs = VecEnv::new(config.num_envs, &league, config.threads, league_seed)?;
+    envs.set_first_move_rate(config.first_move_rate);
+    envs.set_rewards(&Rewards { gamma: config.gamma, ..config.rewards.clone() });
+    envs.set_illegal_moves(config.illegal_moves);
+    let mut opt = Adam::new(bot.var_store(), config.learning_rate);
+
+    let start_epoch = match resume {
+        Some(path) => {
+            let epochs = load_checkpoint(path, &mut bot, &mut opt)?;
+            println!("Resuming from {} after {} epochs", path, epochs);
+            epochs
+        },
+        None => 0
+    };
+
+    if config.checkpoint_every > 0 {
+        fs::create_dir_all(&config.checkpoint_dir)?;
+    }
+
+    let mut log = match &config.metrics {
+        Some(path) => Some(MetricsLog::open(path)?),
+        None => None
+    };
+    let mut random = config.seed.map_or_else(RandomEngine::new, RandomEngine::seeded);
+    let mut solver = SearchEngine::new(config.solver_depth);
+    let started = Instant::now();
+    bot.set_inference(Inference::Greedy);
+
+    let n = config.num_envs;
+    let horizon = config.steps_per_epoch.div_ceil(n).max(1);
+
+    for epoch_idx in start_epoch..config.epochs {
+        let seed = if epoch_idx == start_epoch { config.seed } else { None };
+        let mut obs = envs.reset(seed);
+        let mut all_obs = Vec::with_capacity(horizon);
+        let mut all_masks = Vec::with_capacity(horizon);
+        let mut all_actions = Vec::with_capacity(horizon * n);
+        let mut all_log_probs = Vec::with_capacity(horizon);
+        let mut values = Vec::with_capacity(horizon);
+        let mut rewards = Vec::with_capacity(horizon);
+        let mut dones = Vec::with_capacity(horizon);
+        let mut wins = 0;
+
+        for _ in 0..horizon {
+            let masks = envs.legal_masks();
+            let (actions, log_probs, value) = tch::no_grad(|| {
+                let log_probs = mask_logits(&bot.forward(&obs), &masks).log_softmax(1, Float);
+                let actions = log_probs.exp().multinomial(1, true);
+                (actions.squeeze_dim(1), log_probs.gather(1, &actions, false).squeeze_dim(1), bot.forward_value(&obs).unwrap())
+            });
+            let actions = Vec::<i64>::try_from(actions)?;
+            let step = envs.step(&actions);
+
+            all_obs.push(obs);
+            all_masks.push(masks);
+            all_actions.extend_from_slice(&actions);
+            all_log_probs.push(log_probs);
+            values.push(Vec::<f64>::try_from(value)?);
+            rewards.push(step.rewards);
+            // Truncated episodes are cut off like finished ones: their next
+            // observation already belongs to a new episode.
+            dones.push(step.terminated.iter().zip(&step.truncated).map(|(a, b)| *a || *b).collect::<Vec<bool>>());
+            wins += step.infos.iter().filter(|info| info.winner == Some(info.agent_piece)).count();
+            league.record(&step.infos, dones.last().unwrap());
+            obs = step.obs;
+        }
+        values.push(Vec::<f64>::try_from(tch::no_grad(|| bot.forward_value(&obs).unwrap()))?);
+
+        // Advantages are computed per environment and laid out time-major to match the stacked rollout.
+        let mut flat_advantages = vec![0.0; horizon * n];
+        let mut flat_returns = vec![0.0; horizon * n];
+        for i in 0..n {
+            let env_rewards: Vec<f64> = rewards.iter().map(|r| r[i]).collect();
+            let env_values: Vec<f64> = values.iter().map(|v| v[i]).collect();
+            let env_dones: Vec<bool> = dones.iter().map(|d| d[i]).collect();
+            let env_advantages = advantages(&env_rewards, &env_values, &env_dones, config.gamma, config.gae_lambda);
+            for (t, advantage) in env_advantages.into_iter().enumerate() {
+                flat_advantages[t * n + i] = advantage;
+                flat_returns[t * n + i] = advantage + env_values[t];
+            }
+        }
+        let mean = flat_advantages.iter().sum::<f64>() / flat_advantages.len() as f64;
+        let std = (flat_advantages.iter().map(|a| (a - mean).powi(2)).sum::<f64>() / flat_advantages.len() as f64).sqrt();
+        let normalized: Vec<f64> = flat_advantages.iter().map(|a| (a - mean) / (std + 1e-8)).collect();
+
+        let obs_batch = Tensor::cat(&all_obs, 0);
+        let mask_batch = Tensor::cat(&all_masks, 0);
+        let action_batch = Tensor::from_slice(&all_actions).unsqueeze(1);
+        let old_log_probs = Tensor::cat(&all_log_probs, 0);
+        let advantage_batch = Tensor::from_slice(&normalized).to_kind(Float);
+        let return_batch = Tensor::from_slice(&flat_returns).to_kind(Float);
+
+        let mut indices: Vec<i64> = (0..(horizon * n) as i64).collect();
+        let mut losses = Vec::new();
+        let mut entropies = Vec::new();
+        for _ in 0..config.ppo_epochs {
+            indices.shuffle(&mut rng);
+            for chunk in indices.chunks(config.minibatch_size) {
+                let idx = Tensor::from_slice(chunk);
+                let mb_obs = obs_batch.index_select(0, &idx);
+                let log_probs = mask_logits(&bot.forward(&mb_obs), &mask_batch.index_select(0, &idx)).log_softmax(1, Float);
+                let new_log_probs = log_probs.gather(1, &action_batch.index_select(0, &idx), false).squeeze_dim(1);
+                let advantage = advantage_batch.index_select(0, &idx);
+
+                let ratio = (new_log_probs - old_log_probs.index_select(0, &idx)).exp();
+                let clipped = ratio.clamp(1.0 - config.clip_epsilon, 1.0 + config.clip_epsilon);
+                let policy_loss = -(&ratio * &advantage).min_other(&(clipped * &advantage)).mean(Float);
+                let value_loss = bot.forward_value(&mb_obs).unwrap().mse_loss(&return_batch.index_select(0, &idx), Reduction::Mean);
+                let entropy = -(log_probs.exp() * &log_probs).sum_dim_intlist(1, false, Float).mean(Float);
+
+                let loss = policy_loss + value_loss * config.value_coef - &entropy * config.entropy_coef;
+                opt.backward_step(&loss);
+                losses.push(f64::try_from(&loss)?);
+                entropies.push(f64::try_from(&entropy)?);
+            }
+        }
+
+        let episodes = dones.iter().flatten().filter(|&&done| done).count() as i64;
+        let sum_r: f64 = rewards.iter().flatten().sum();
+        let loss = losses.iter().sum::<f64>() / losses.len() as f64;
+        println!(
+            "epoch: {:<3} episodes: {:<5} avg reward per episode: {:.2} wins: {:<5} loss: {:.4}",
+            epoch_idx,
+            episodes,
+            sum_r / episodes.max(1) as f64,
+            wins,
+            loss
+        );
+        println!("league win rates: {}", league.summary());
+
+        if let Some(log) = log.as_mut() {
+            log.write(&EpochMetrics {
+                epoch: epoch_idx,
+                episodes,
+                avg_reward: sum_r / episodes.max(1) as f64,
+                loss,
+                entropy: entropies.iter().sum::<f64>() / entropies.len() as f64,
+                win_rate_random: metrics::win_rate(&mut bot, &mut random, config.eval_games),
+                win_rate_solver: metrics::win_rate(&mut bot, &mut solver, config.eval_games),
+                wall_time: started.elapsed().as_secs_f64()
+            })?;
+        }
+
+        if config.checkpoint_every > 0 && (epoch_idx + 1) % config.checkpoint_every == 0 {
+            let path = format!("{}/epoch-{}.ot", config.checkpoint_dir, epoch_idx + 1);
+            save_checkpoint(&path, epoch_idx + 1, &bot, &opt)?;
+            if league.add_checkpoint(&path)? {
+                envs.set_opponents(&league, league_seed)?;
+            }
+        }
+    }
+
+    bot.set_epochs(config.epochs.max(start_epoch));
+    Ok(bot.save(&config.output)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advantages_with_lambda_one_are_discounted_returns_minus_values() {
+        let rewards = [0.0, 0.0, 1.0];
+        let values = [0.5, 0.5, 0.5, 9.0];
+        let dones = [false, false, true];
+        let result = advantages(&rewards, &values, &dones, 0.5, 1.0);
+        assert_eq!(result, vec![0.25 - 0.5, 0.5 - 0.5, 1.0 - 0.5]);
+    }
+
+    #[test]
+    fn test_advantages_do_not_bootstrap_across_episodes() {
+        let rewards = [1.0, 0.0];
+        let values = [0.0, 10.0, 10.0];
+        let dones = [true, false];
+        let result = advantages(&rewards, &values, &dones, 1.0, 0.0);
+        assert_eq!(result, vec![1.0, 0.0]);
+    }
+}
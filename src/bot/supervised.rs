@@ -0,0 +1,89 @@
+use crate::bot::bot::{mask_logits, Bot, TrainError};
+use crate::bot::config::TrainConfig;
+use crate::bot::dataset::Batch;
+use crate::dataset::Dataset;
+use crate::bot::optim::Adam;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use tch::{Kind::Float, Reduction, TchError, Tensor};
+
+/// Loss and accuracy over a set of positions.
+#[derive(Clone, Copy, Debug, Default)]
+struct Scores {
+    loss: f64,
+    /// Fraction of positions where the top-rated legal column is one of the best moves.
+    accuracy: f64
+}
+
+/// Cross-entropy of the masked policy against the best moves, plus the value
+/// error when the network has a value head. Values are scaled by the win reward
+/// to match the returns the reinforcement learning trainers fit.
+fn batch_loss(bot: &Bot, batch: &Batch, config: &TrainConfig) -> (Tensor, f64) {
+    let log_probs = mask_logits(&bot.forward(&batch.obs), &batch.masks).log_softmax(1, Float);
+    let policy_loss = -(&batch.targets * &log_probs).sum_dim_intlist(1, false, Float).mean(Float);
+    let loss = match bot.forward_value(&batch.obs) {
+        Some(value) => {
+            let target = &batch.values * config.rewards.win;
+            policy_loss + value.mse_loss(&target, Reduction::Mean) * config.value_coef
+        },
+        None => policy_loss
+    };
+
+    // A predicted column is one of the best moves exactly when its target probability is positive.
+    let predicted = log_probs.argmax(1, true);
+    let accuracy = batch.targets.gather(1, &predicted, false).gt(0.0).to_kind(Float).mean(Float).double_value(&[]);
+    (loss, accuracy)
+}
+
+/// Scores the positions at `indices` in minibatches of `batch_size`.
+fn evaluate(bot: &Bot, data: &Batch, indices: &[usize], config: &TrainConfig) -> Result<Scores, TchError> {
+    let mut scores = Scores::default();
+    for chunk in indices.chunks(config.batch_size) {
+        let (loss, accuracy) = tch::no_grad(|| batch_loss(bot, &data.select(chunk), config));
+        let weight = chunk.len() as f64 / indices.len() as f64;
+        scores.loss += f64::try_from(loss)? * weight;
+        scores.accuracy += accuracy * weight;
+    }
+    Ok(scores)
+}
+
+/// Trains the network on the labelled positions in `path` (see `Dataset`).
+///
+/// A `validation_split` fraction of the shuffled positions is held out. Each
+/// epoch makes one pass over the rest in minibatches of `batch_size` and
+/// reports loss and best-move accuracy on both sets.
+pub fn train(config: &TrainConfig, path: &str) -> Result<(), TrainError> {
+    if let Some(seed) = config.seed {
+        tch::manual_seed(seed as i64);
+    }
+    let mut rng = config.seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+
+    let dataset = Dataset::load(path).map_err(|err| TrainError::Dataset(path.to_string(), err))?;
+    let data = dataset.encode();
+    let mut indices: Vec<usize> = (0..dataset.positions.len()).collect();
+    indices.shuffle(&mut rng);
+    let validation = (indices.len() as f64 * config.validation_split).round() as usize;
+    let (validation, mut training) = (indices[..validation].to_vec(), indices[validation..].to_vec());
+    println!("{} positions: {} for training, {} for validation", indices.len(), training.len(), validation.len());
+
+    let mut bot = Bot::with_architecture(&config.architecture);
+    bot.set_value_scale(config.rewards.win);
+    let mut opt = Adam::new(bot.var_store(), config.learning_rate);
+
+    for epoch_idx in 0..config.epochs {
+        training.shuffle(&mut rng);
+        for chunk in training.chunks(config.batch_size) {
+            let (loss, _) = batch_loss(&bot, &data.select(chunk), config);
+            opt.backward_step(&loss);
+        }
+
+        let train_scores = evaluate(&bot, &data, &training, config)?;
+        let validation_scores = evaluate(&bot, &data, &validation, config)?;
+        println!(
+            "epoch: {:<3} loss: {:.4} accuracy: {:.3} validation loss: {:.4} validation accuracy: {:.3}",
+            epoch_idx, train_scores.loss, train_scores.accuracy, validation_scores.loss, validation_scores.accuracy
+        );
+    }
+
+    bot.set_epochs(config.epochs);
+    Ok(bot.save(&config.output)?)
+}
@@ -0,0 +1,167 @@
+use crate::bot::env::{Env, Environment, IllegalMoves, Info, Rewards};
+use crate::bot::league::League;
+use crate::engine::SpecError;
+use std::thread;
+use tch::Tensor;
+
+/// Several `Env`s stepped in lockstep so the model can act on all of them with one batched forward pass.
+///
+/// Environments whose episode ends are reset immediately: the rewards and flags
+/// returned by `step` describe the finished episode, while the observation in
+/// that row already belongs to the next one.
+pub struct VecEnv {
+    envs: Vec<Env>,
+    threads: usize
+}
+
+/// Result of stepping every environment once. Row `i` of each field belongs to environment `i`.
+pub struct VecStep {
+    /// Observations stacked into an `[n, 42]` tensor.
+    pub obs: Tensor,
+    /// Legal column masks for `obs`, stacked into an `[n, 7]` boolean tensor.
+    pub masks: Tensor,
+    pub rewards: Vec<f64>,
+    pub terminated: Vec<bool>,
+    pub truncated: Vec<bool>,
+    pub infos: Vec<Info>
+}
+
+impl VecEnv {
+    /// Creates `n` environments playing against `league`, stepped on up to
+    /// `threads` OS threads. Environment `i` gets engines seeded from `seed + i`.
+    pub fn new(n: usize, league: &League, threads: usize, seed: u64) -> Result<VecEnv, SpecError> {
+        let envs = (0..n)
+            .map(|i| Ok(Env::new(league.engines(seed.wrapping_add(i as u64))?)))
+            .collect::<Result<Vec<Env>, SpecError>>()?;
+        Ok(VecEnv { envs, threads: threads.max(1) })
+    }
+
+    /// Rebuilds every environment's opponents from `league`, e.g. after it gained a
+    /// checkpoint. Each environment switches once its current episode ends.
+    pub fn set_opponents(&mut self, league: &League, seed: u64) -> Result<(), SpecError> {
+        for (i, env) in self.envs.iter_mut().enumerate() {
+            env.set_opponents(league.engines(seed.wrapping_add(i as u64))?);
+        }
+        Ok(())
+    }
+
+    pub fn set_illegal_moves(&mut self, illegal_moves: IllegalMoves) {
+        for env in self.envs.iter_mut() {
+            env.set_illegal_moves(illegal_moves);
+        }
+    }
+
+    pub fn set_rewards(&mut self, rewards: &Rewards) {
+        for env in self.envs.iter_mut() {
+            env.set_rewards(rewards.clone());
+        }
+    }
+
+    /// Fraction of episodes in which the agent plays piece 1, see `Env::set_first_move_rate`.
+    pub fn set_first_move_rate(&mut self, rate: f64) {
+        for env in self.envs.iter_mut() {
+            env.set_first_move_rate(rate);
+        }
+    }
+
+    /// Resets every environment. With a seed, environment `i` is seeded with `seed + i`.
+    pub fn reset(&mut self, seed: Option<u64>) -> Tensor {
+        let obs: Vec<Tensor> = self
+            .envs
+            .iter_mut()
+            .enumerate()
+            .map(|(i, env)| env.reset(seed.map(|s| s.wrapping_add(i as u64))).0)
+            .collect();
+        Tensor::stack(&obs, 0)
+    }
+
+    /// Applies `actions[i]` to environment `i`.
+    pub fn step(&mut self, actions: &[i64]) -> VecStep {
+        assert_eq!(actions.len(), self.envs.len());
+
+        let chunk_size = self.envs.len().div_ceil(self.threads).max(1);
+        let results: Vec<(Tensor, f64, bool, bool, Info)> = if self.threads == 1 {
+            step_all(&mut self.envs, actions)
+        } else {
+            thread::scope(|scope| {
+                let handles: Vec<_> = self
+                    .envs
+                    .chunks_mut(chunk_size)
+                    .zip(actions.chunks(chunk_size))
+                    .map(|(envs, actions)| scope.spawn(move || step_all(envs, actions)))
+                    .collect();
+                handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+            })
+        };
+
+        let mut obs = Vec::with_capacity(results.len());
+        let mut rewards = Vec::with_capacity(results.len());
+        let mut terminated = Vec::with_capacity(results.len());
+        let mut truncated = Vec::with_capacity(results.len());
+        let mut infos = Vec::with_capacity(results.len());
+        for result in results {
+            obs.push(result.0);
+            rewards.push(result.1);
+            terminated.push(result.2);
+            truncated.push(result.3);
+            infos.push(result.4);
+        }
+
+        VecStep { obs: Tensor::stack(&obs, 0), masks: self.legal_masks(), rewards, terminated, truncated, infos }
+    }
+
+    pub fn action_space(&self) -> i64 {
+        self.envs[0].action_space()
+    }
+
+    pub fn observation_space(&self) -> Vec<i64> {
+        self.envs[0].observation_space()
+    }
+
+    /// Legal columns of every environment.
+    pub fn legal_actions(&self) -> Vec<Vec<i64>> {
+        self.envs.iter().map(|env| env.legal_actions()).collect()
+    }
+
+    /// Legal column masks stacked into an `[n, 7]` boolean tensor.
+    pub fn legal_masks(&self) -> Tensor {
+        let masks: Vec<Tensor> = self.envs.iter().map(|env| env.legal_mask()).collect();
+        Tensor::stack(&masks, 0)
+    }
+}
+
+/// Steps each environment in `envs`, resetting the ones whose episode ends.
+fn step_all(envs: &mut [Env], actions: &[i64]) -> Vec<(Tensor, f64, bool, bool, Info)> {
+    envs.iter_mut()
+        .zip(actions)
+        .map(|(env, &action)| {
+            let (obs, reward, terminated, truncated, info) = env.step(action);
+            let obs = if terminated || truncated { env.reset(None).0 } else { obs };
+            (obs, reward, terminated, truncated, info)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::Column;
+
+    #[test]
+    fn test_forbidden_moves_end_the_episode_on_worker_threads() {
+        let envs = (0..4).map(|_| Env::new(vec![(String::from("first column"), Box::new(Column(0)), 1.0)])).collect();
+        let mut envs = VecEnv { envs, threads: 2 };
+        envs.set_illegal_moves(IllegalMoves::Forbid);
+        envs.reset(Some(0));
+
+        // Column 0 fills after three moves each, so the fourth is illegal.
+        for _ in 0..3 {
+            let step = envs.step(&[0; 4]);
+            assert!(step.terminated.iter().all(|&done| !done));
+        }
+        let step = envs.step(&[0; 4]);
+        assert!(step.terminated.iter().all(|&done| done));
+        assert!(step.infos.iter().all(|info| info.illegal_action && info.winner.is_none()));
+        assert!(step.rewards.iter().all(|&reward| reward == Rewards::default().illegal));
+    }
+}
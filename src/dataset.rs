@@ -0,0 +1,324 @@
+// Datasets are only read back for supervised training, which needs the torch feature.
+#![cfg_attr(not(feature = "torch"), allow(dead_code))]
+
+use crate::board::{Board, WIDTH};
+use crate::eval;
+use crate::search::{self, WIN_SCORE};
+use rand::{rngs::StdRng, seq::IteratorRandom, Rng, SeedableRng};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::thread;
+
+const CSV_HEADER: &str = "moves,best,value";
+
+/// A position labelled with its best moves and game value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Position {
+    /// Columns played from the empty board, alternating from piece 1.
+    pub moves: Vec<usize>,
+    /// Columns that reach the best achievable outcome for the side to move.
+    pub best: Vec<usize>,
+    /// Outcome for the side to move: 1 win, 0 draw, -1 loss under perfect play, or
+    /// a heuristic estimate in between when the search did not reach the end.
+    pub value: f64
+}
+
+#[derive(Debug)]
+pub enum DatasetError {
+    Io(io::Error),
+    /// A line that is not a valid record, with its 1-based line number.
+    Malformed(usize, String),
+    /// The file holds no positions.
+    Empty
+}
+
+impl fmt::Display for DatasetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DatasetError::Io(err) => write!(f, "{}", err),
+            DatasetError::Malformed(line, msg) => write!(f, "line {}: {}", line, msg),
+            DatasetError::Empty => write!(f, "no positions found")
+        }
+    }
+}
+
+impl From<io::Error> for DatasetError {
+    fn from(err: io::Error) -> DatasetError {
+        DatasetError::Io(err)
+    }
+}
+
+impl Position {
+    /// Builds a position from move strings of 1-based columns, e.g. `4453` and
+    /// `34`, checking that the moves form an unfinished game and the best moves
+    /// are playable in it.
+    pub fn new(moves: &str, best: &str, value: &str) -> Result<Position, String> {
+        let moves = columns(moves)?;
+        let best = columns(best)?;
+        let value = value.parse::<f64>().ok().filter(|v| (-1.0..=1.0).contains(v)).ok_or_else(|| format!("invalid value `{}`", value))?;
+
+        let board = replay(&moves)?;
+        if board.finished() {
+            return Err(String::from("the game is already over"))
+        }
+        if best.is_empty() || best.iter().any(|&col| board.first_available_row_for_column(col).is_none()) {
+            return Err(String::from("best moves must be playable columns"))
+        }
+        Ok(Position { moves, best, value })
+    }
+
+    /// Parses a CSV record such as `4453,34,1`. The empty board has no moves.
+    pub fn from_csv(line: &str) -> Result<Position, String> {
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let [moves, best, value] = fields[..] else {
+            return Err(format!("expected `{}`, got `{}`", CSV_HEADER, line))
+        };
+        Position::new(moves, best, value)
+    }
+
+    /// Parses a JSON record such as `{"moves":"4453","best":"34","value":1}`.
+    pub fn from_json(line: &str) -> Result<Position, String> {
+        let field = |key| json_field(line, key).ok_or_else(|| format!("missing `{}`", key));
+        Position::new(field("moves")?, field("best")?, field("value")?)
+    }
+
+    pub fn to_csv(&self) -> String {
+        format!("{},{},{}", move_string(&self.moves), move_string(&self.best), self.value)
+    }
+
+    pub fn to_json(&self) -> String {
+        format!("{{\"moves\":\"{}\",\"best\":\"{}\",\"value\":{}}}", move_string(&self.moves), move_string(&self.best), self.value)
+    }
+
+    /// The board after playing `moves`.
+    pub fn board(&self) -> Board {
+        replay(&self.moves).unwrap()
+    }
+}
+
+/// Parses a move string of 1-based columns.
+fn columns(moves: &str) -> Result<Vec<usize>, String> {
+    moves
+        .chars()
+        .map(|c| match c.to_digit(10) {
+            Some(col) if (1..=WIDTH as u32).contains(&col) => Ok(col as usize - 1),
+            _ => Err(format!("invalid column `{}`", c))
+        })
+        .collect()
+}
+
+fn move_string(cols: &[usize]) -> String {
+    cols.iter().map(|&col| char::from(b'1' + col as u8)).collect()
+}
+
+/// Plays `moves` from the empty board, alternating from piece 1.
+fn replay(moves: &[usize]) -> Result<Board, String> {
+    let mut board = Board::new();
+    for (i, &col) in moves.iter().enumerate() {
+        if board.finished() || board.place(col, if i % 2 == 0 { 1 } else { 2 }).is_err() {
+            return Err(format!("illegal move {} at ply {}", col + 1, i + 1))
+        }
+    }
+    Ok(board)
+}
+
+/// The value of a flat JSON object's `key`, without quotes. Enough for the
+/// records `Position::to_json` writes.
+fn json_field<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+    let start = line.find(&format!("\"{}\"", key))? + key.len() + 2;
+    let rest = line[start..].trim_start().strip_prefix(':')?.trim_start();
+    match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next(),
+        None => rest.split([',', '}']).next().map(str::trim)
+    }
+}
+
+/// Labels the position after `moves` by searching every legal column `depth`
+/// plies deep. The labels are exact when the search reaches the end of the game.
+pub fn label(moves: Vec<usize>, depth: u32) -> Position {
+    let board = replay(&moves).unwrap();
+    let piece = board.to_move();
+    let scores: Vec<(usize, f64)> = board
+        .available_columns()
+        .into_iter()
+        .map(|col| {
+            let mut next = board.clone();
+            let _ = next.place(col, piece);
+            let score = if next.winner() == Some(piece) {
+                WIN_SCORE - next.plies() as i32
+            } else {
+                -search::negamax(&next, piece ^ 3, depth.saturating_sub(1), -WIN_SCORE, WIN_SCORE)
+            };
+            (col, value_of(score))
+        })
+        .collect();
+
+    let value = scores.iter().map(|&(_, value)| value).fold(f64::NEG_INFINITY, f64::max);
+    let best = scores.iter().filter(|&&(_, v)| v == value).map(|&(col, _)| col).collect();
+    Position { moves, best, value }
+}
+
+/// Wins and losses become 1 and -1 whatever their length. Heuristic scores at the
+/// search horizon are scaled into `[-0.5, 0.5]` so they never look like a forced result.
+fn value_of(score: i32) -> f64 {
+    if score.abs() > eval::MAX_EVAL {
+        score.signum() as f64
+    } else {
+        score as f64 / (2 * eval::MAX_EVAL) as f64
+    }
+}
+
+/// Settings for `generate`.
+#[derive(Clone, Debug)]
+pub struct DatasetConfig {
+    pub positions: usize,
+    /// Random playouts stop after a number of plies drawn from `0..=max_plies`.
+    pub max_plies: usize,
+    /// Search depth used for labelling.
+    pub depth: u32,
+    pub workers: usize,
+    pub seed: u64,
+    /// CSV file, or JSON lines when it ends in `.jsonl`.
+    pub output: String
+}
+
+impl Default for DatasetConfig {
+    fn default() -> DatasetConfig {
+        DatasetConfig { positions: 10_000, max_plies: 30, depth: 8, workers: 4, seed: 0, output: String::from("positions.csv") }
+    }
+}
+
+/// Samples positions from random playouts truncated at random depths, skipping
+/// finished games and positions already drawn or their mirror images, then
+/// labels them across `config.workers` threads and writes them to `config.output`.
+/// Returns the number of positions written, which is lower than asked for when
+/// `max_plies` allows too few distinct positions.
+pub fn generate(config: &DatasetConfig) -> io::Result<usize> {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut seen = HashSet::new();
+    let mut sampled = Vec::new();
+
+    for _ in 0..config.positions.saturating_mul(100) {
+        if sampled.len() == config.positions {
+            break
+        }
+
+        let plies = rng.gen_range(0..=config.max_plies);
+        let mut board = Board::new();
+        let mut moves = Vec::with_capacity(plies);
+        while moves.len() < plies && !board.finished() {
+            let col = board.available_columns().into_iter().choose(&mut rng).unwrap();
+            let _ = board.place(col, board.to_move());
+            moves.push(col);
+        }
+        if !board.finished() && seen.insert(board.canonical_key()) {
+            sampled.push(moves);
+        }
+    }
+
+    let chunk = sampled.len().div_ceil(config.workers.max(1)).max(1);
+    let labelled: Result<Vec<Vec<Position>>, _> = thread::scope(|scope| {
+        let handles: Vec<_> = sampled
+            .chunks(chunk)
+            .map(|moves| scope.spawn(|| moves.iter().map(|moves| label(moves.clone(), config.depth)).collect::<Vec<_>>()))
+            .collect();
+        handles.into_iter().map(|handle| handle.join()).collect()
+    });
+    let positions = labelled.map_err(|_| io::Error::other("a labelling thread panicked"))?.concat();
+
+    Dataset { positions }.save(&config.output)?;
+    Ok(sampled.len())
+}
+
+/// Labelled positions for supervised training. On disk they are CSV with a
+/// `moves,best,value` header, or JSON lines for files ending in `.jsonl`, with
+/// moves in move-string notation:
+///
+/// ```text
+/// moves,best,value
+/// ,4,1
+/// 4453,34,1
+/// 44444,5,0
+/// ```
+pub struct Dataset {
+    pub positions: Vec<Position>
+}
+
+impl Dataset {
+    /// Reads a dataset, skipping blank lines, lines starting with `#` and the CSV
+    /// header. A file without any positions is an error.
+    pub fn load(path: &str) -> Result<Dataset, DatasetError> {
+        let parse = if path.ends_with(".jsonl") { Position::from_json } else { Position::from_csv };
+        let mut positions = Vec::new();
+        for (i, line) in fs::read_to_string(path)?.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line == CSV_HEADER {
+                continue
+            }
+            positions.push(parse(line).map_err(|msg| DatasetError::Malformed(i + 1, msg))?);
+        }
+        if positions.is_empty() {
+            return Err(DatasetError::Empty)
+        }
+        Ok(Dataset { positions })
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        if path.ends_with(".jsonl") {
+            for position in &self.positions {
+                writeln!(out, "{}", position.to_json())?;
+            }
+        } else {
+            writeln!(out, "{}", CSV_HEADER)?;
+            for position in &self.positions {
+                writeln!(out, "{}", position.to_csv())?;
+            }
+        }
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_round_trip() {
+        for line in [",4,1", "4453,34,0.5", "44444,5,0"] {
+            let position = Position::from_csv(line).unwrap();
+            assert_eq!(position.to_csv(), line);
+            assert_eq!(Position::from_json(&position.to_json()).unwrap(), position);
+        }
+        let position = Position::from_csv("4453,34,1").unwrap();
+        assert_eq!(position.moves, vec![3, 3, 4, 2]);
+        assert_eq!(position.best, vec![2, 3]);
+        assert_eq!(position.board().to_move(), 1);
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_records() {
+        assert!(Position::from_csv("4453,34").is_err());
+        assert!(Position::from_csv("48,1,0").is_err());
+        assert!(Position::from_csv("4444444,1,0").is_err());
+        assert!(Position::from_csv(",4,2").is_err());
+        // Four in a row for piece 1 in column 1 ends the game.
+        assert!(Position::from_csv("1212121,3,0").is_err());
+    }
+
+    #[test]
+    fn test_label_finds_the_only_winning_move() {
+        let position = label(vec![0, 1, 0, 1, 0, 1], 2);
+        assert_eq!(position.best, vec![0]);
+        assert_eq!(position.value, 1.0);
+    }
+
+    #[test]
+    fn test_label_scales_heuristic_values_below_a_win() {
+        let position = label(Vec::new(), 2);
+        assert!(position.value.abs() <= 0.5);
+        assert!(!position.best.is_empty());
+    }
+}
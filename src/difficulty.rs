@@ -0,0 +1,74 @@
+use crate::engine::{BlunderEngine, Engine, GreedyRandomEngine, SearchEngine};
+
+/// Named strength levels for the bot opponent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Difficulty {
+    Beginner,
+    Intermediate,
+    Expert,
+    /// The deepest search we can afford interactively, with no blunders. Strong,
+    /// but not a solver: it can still misjudge positions beyond its horizon.
+    Master
+}
+
+impl Difficulty {
+    pub fn parse(name: &str) -> Option<Difficulty> {
+        match name {
+            "beginner" => Some(Difficulty::Beginner),
+            "intermediate" => Some(Difficulty::Intermediate),
+            "expert" => Some(Difficulty::Expert),
+            "master" => Some(Difficulty::Master),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Beginner => "Beginner",
+            Difficulty::Intermediate => "Intermediate",
+            Difficulty::Expert => "Expert",
+            Difficulty::Master => "Master"
+        }
+    }
+
+    /// Probability of replacing the engine's move with a random one.
+    pub fn blunder_rate(&self) -> f64 {
+        match self {
+            Difficulty::Beginner => 0.25,
+            Difficulty::Intermediate => 0.1,
+            Difficulty::Expert => 0.02,
+            Difficulty::Master => 0.0
+        }
+    }
+
+    /// One-line summary for the game header.
+    pub fn description(&self) -> String {
+        let engine = match self {
+            Difficulty::Beginner => String::from("takes wins and blocks losses"),
+            _ => format!("searches {} moves ahead", self.search_depth())
+        };
+        format!("{}: {}, {:.0}% blunders", self.name(), engine, self.blunder_rate() * 100.0)
+    }
+
+    pub fn engine(&self, seed: u64) -> Box<dyn Engine + Send> {
+        let engine: Box<dyn Engine + Send> = match self {
+            Difficulty::Beginner => Box::new(GreedyRandomEngine::seeded(seed)),
+            _ => Box::new(SearchEngine::new(self.search_depth()))
+        };
+
+        if self.blunder_rate() > 0.0 {
+            Box::new(BlunderEngine::new(engine, self.blunder_rate(), seed))
+        } else {
+            engine
+        }
+    }
+
+    fn search_depth(&self) -> u32 {
+        match self {
+            Difficulty::Beginner => 1,
+            Difficulty::Intermediate => 4,
+            Difficulty::Expert => 7,
+            Difficulty::Master => 10
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use crate::board::{Board, WIDTH};
+use crate::book::OpeningBook;
+#[cfg(feature = "torch")]
+use crate::bot::bot::Bot;
+use crate::mlp::{Mlp, MlpEngine};
+use crate::search;
+use rand::{rngs::StdRng, seq::IteratorRandom, Rng, SeedableRng};
+use std::fmt;
+
+/// Something that can pick a move for either side of a game.
+pub trait Engine {
+    fn name(&self) -> String;
+
+    /// Chooses a column for `piece`. Only called on boards that are not finished.
+    fn choose_move(&mut self, board: &Board, piece: u8) -> usize;
+
+    /// The engine's view of the position for `piece`, for engines that have a
+    /// meaningful move distribution.
+    fn analyze(&mut self, _board: &Board, _piece: u8) -> Option<Analysis> {
+        None
+    }
+}
+
+/// How an engine rates a position before choosing a move.
+#[derive(Clone, Debug)]
+pub struct Analysis {
+    /// Probability of playing each column. Illegal columns get zero.
+    pub policy: Vec<f64>,
+    /// Expected outcome for the side to move in `[-1, 1]`, if the engine estimates one.
+    pub value: Option<f64>
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SpecError {
+    UnknownEngine(String),
+    InvalidArgument(String),
+    Unreadable(String)
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SpecError::UnknownEngine(spec) => write!(f, "unknown engine `{}`", spec),
+            SpecError::InvalidArgument(spec) => write!(f, "invalid engine argument in `{}`", spec),
+            SpecError::Unreadable(msg) => write!(f, "{}", msg)
+        }
+    }
+}
+
+/// Builds an engine from a spec such as `random`, `greedy`, `heuristic`, `search:6`,
+/// `model:model.ot`, `checkpoint:checkpoints/epoch-50.ot` or `weights:model.c4nn`
+/// (an exported network, see `Mlp`). `model:` and `checkpoint:` need the torch feature.
+/// Prefixing a spec with `book:<file>:` makes the engine consult an opening book
+/// first, e.g. `book:book.bin:search:8`. `seed` makes randomized engines reproducible.
+pub fn from_spec(spec: &str, seed: u64) -> Result<Box<dyn Engine + Send>, SpecError> {
+    let (name, arg) = match spec.split_once(':') {
+        Some((name, arg)) => (name, Some(arg)),
+        None => (spec, None)
+    };
+
+    match (name, arg) {
+        ("random", None) => Ok(Box::new(RandomEngine::seeded(seed))),
+        ("greedy", None) => Ok(Box::new(GreedyRandomEngine::seeded(seed))),
+        ("heuristic", None) => Ok(Box::new(HeuristicEngine)),
+        ("search", depth) => {
+            let depth = depth.unwrap_or("4").parse().map_err(|_| SpecError::InvalidArgument(spec.to_string()))?;
+            Ok(Box::new(SearchEngine::new(depth)))
+        },
+        ("book", Some(rest)) => {
+            let (path, inner) = rest.split_once(':').ok_or_else(|| SpecError::InvalidArgument(spec.to_string()))?;
+            let book = OpeningBook::load(path).map_err(|err| SpecError::Unreadable(err.to_string()))?;
+            Ok(Box::new(BookEngine::new(book, from_spec(inner, seed)?)))
+        },
+        #[cfg(feature = "torch")]
+        ("model", Some(path)) => {
+            let mut bot = Bot::open(path).map_err(|err| SpecError::Unreadable(err.to_string()))?;
+            bot.seed(seed);
+            Ok(Box::new(bot))
+        },
+        #[cfg(feature = "torch")]
+        ("checkpoint", Some(path)) => {
+            let mut bot = Bot::open_checkpoint(path).map_err(|err| SpecError::Unreadable(err.to_string()))?;
+            bot.seed(seed);
+            Ok(Box::new(bot))
+        },
+        ("weights", Some(path)) => {
+            let mlp = Mlp::load(path).map_err(|err| SpecError::Unreadable(err.to_string()))?;
+            Ok(Box::new(MlpEngine::new(mlp, seed)))
+        },
+        #[cfg(not(feature = "torch"))]
+        ("model" | "checkpoint", Some(_)) => {
+            Err(SpecError::Unreadable(format!("`{}` needs a build with the torch feature", spec)))
+        },
+        _ => Err(SpecError::UnknownEngine(spec.to_string()))
+    }
+}
+
+/// Plays a uniformly random legal column.
+pub struct RandomEngine {
+    rng: StdRng
+}
+
+impl RandomEngine {
+    pub fn new() -> RandomEngine {
+        RandomEngine { rng: StdRng::from_entropy() }
+    }
+
+    pub fn seeded(seed: u64) -> RandomEngine {
+        RandomEngine { rng: StdRng::seed_from_u64(seed) }
+    }
+}
+
+impl Engine for RandomEngine {
+    fn name(&self) -> String {
+        String::from("random")
+    }
+
+    fn choose_move(&mut self, board: &Board, _piece: u8) -> usize {
+        board.available_columns().into_iter().choose(&mut self.rng).unwrap()
+    }
+
+    fn analyze(&mut self, board: &Board, _piece: u8) -> Option<Analysis> {
+        let cols = board.available_columns();
+        let mut policy = vec![0.0; WIDTH];
+        for &col in &cols {
+            policy[col] = 1.0 / cols.len() as f64;
+        }
+        Some(Analysis { policy, value: None })
+    }
+}
+
+/// Plays randomly, but takes immediate wins and blocks the opponent's.
+pub struct GreedyRandomEngine {
+    rng: StdRng
+}
+
+impl GreedyRandomEngine {
+    pub fn seeded(seed: u64) -> GreedyRandomEngine {
+        GreedyRandomEngine { rng: StdRng::seed_from_u64(seed) }
+    }
+}
+
+impl Engine for GreedyRandomEngine {
+    fn name(&self) -> String {
+        String::from("greedy")
+    }
+
+    fn choose_move(&mut self, board: &Board, piece: u8) -> usize {
+        let mut cols = board.non_losing_moves(piece);
+        if cols.is_empty() {
+            cols = board.available_columns();
+        }
+        cols.into_iter().choose(&mut self.rng).unwrap()
+    }
+}
+
+/// Plays the column whose resulting position `eval::evaluate` likes best, taking
+/// immediate wins. Cheap enough for rollouts and as a baseline opponent.
+pub struct HeuristicEngine;
+
+impl Engine for HeuristicEngine {
+    fn name(&self) -> String {
+        String::from("heuristic")
+    }
+
+    fn choose_move(&mut self, board: &Board, piece: u8) -> usize {
+        search::best_move(board, piece, 1).unwrap().0
+    }
+}
+
+/// Plays the best column found by a depth-limited alpha-beta search.
+pub struct SearchEngine {
+    depth: u32
+}
+
+impl SearchEngine {
+    pub fn new(depth: u32) -> SearchEngine {
+        SearchEngine { depth }
+    }
+}
+
+impl Engine for SearchEngine {
+    fn name(&self) -> String {
+        format!("search-{}", self.depth)
+    }
+
+    fn choose_move(&mut self, board: &Board, piece: u8) -> usize {
+        search::best_move(board, piece, self.depth).unwrap().0
+    }
+}
+
+/// Plays from an opening book while the position is in it, deferring to `inner` afterwards.
+pub struct BookEngine {
+    book: OpeningBook,
+    inner: Box<dyn Engine + Send>
+}
+
+impl BookEngine {
+    pub fn new(book: OpeningBook, inner: Box<dyn Engine + Send>) -> BookEngine {
+        BookEngine { book, inner }
+    }
+}
+
+impl Engine for BookEngine {
+    fn name(&self) -> String {
+        format!("book+{}", self.inner.name())
+    }
+
+    fn choose_move(&mut self, board: &Board, piece: u8) -> usize {
+        match self.book.lookup(board) {
+            Some((col, _)) if board.to_move() == piece => col,
+            _ => self.inner.choose_move(board, piece)
+        }
+    }
+}
+
+/// Wraps an engine and replaces its move with a random legal one at a fixed rate.
+pub struct BlunderEngine {
+    inner: Box<dyn Engine + Send>,
+    rate: f64,
+    rng: StdRng
+}
+
+impl BlunderEngine {
+    pub fn new(inner: Box<dyn Engine + Send>, rate: f64, seed: u64) -> BlunderEngine {
+        BlunderEngine { inner, rate, rng: StdRng::seed_from_u64(seed) }
+    }
+}
+
+impl Engine for BlunderEngine {
+    fn name(&self) -> String {
+        format!("{}~{}", self.inner.name(), self.rate)
+    }
+
+    fn choose_move(&mut self, board: &Board, piece: u8) -> usize {
+        if self.rng.gen_bool(self.rate) {
+            board.available_columns().into_iter().choose(&mut self.rng).unwrap()
+        } else {
+            self.inner.choose_move(board, piece)
+        }
+    }
+}
+
+/// Always plays the same column, eventually an illegal move. Gives tests an
+/// opponent whose every move is known in advance.
+#[cfg(test)]
+#[cfg_attr(not(feature = "torch"), allow(dead_code))]
+pub struct Column(pub usize);
+
+#[cfg(test)]
+impl Engine for Column {
+    fn name(&self) -> String {
+        format!("column {}", self.0)
+    }
+
+    fn choose_move(&mut self, _board: &Board, _piece: u8) -> usize {
+        self.0
+    }
+}
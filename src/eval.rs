@@ -0,0 +1,146 @@
+use crate::board::{Board, HEIGHT, WIDTH};
+use std::sync::OnceLock;
+
+/// Upper bound on the magnitude of `evaluate`, kept well below `search::WIN_SCORE`
+/// so heuristic scores never look like forced wins.
+pub const MAX_EVAL: i32 = 400;
+
+const OPEN_THREE: i32 = 5;
+const OPEN_TWO: i32 = 2;
+const CENTER: [i32; WIDTH] = [0, 1, 2, 3, 2, 1, 0];
+/// A threat on the row parity that favours its owner in the endgame.
+const GOOD_THREAT: i32 = 30;
+const OTHER_THREAT: i32 = 10;
+/// Bonus for holding the only useful threats once the board fills up.
+const ZUGZWANG: i32 = 60;
+
+/// Direction steps `(dcol, drow)` covering horizontal, vertical and both diagonals.
+const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+/// Scores a non-terminal board from `piece`'s perspective.
+///
+/// Counts open twos and threes in every four-cell window, pieces near the center
+/// and threats (empty cells completing a four). Threats are weighted by row
+/// parity: with the first player moving on odd turns, zugzwang tends to hand
+/// them the odd rows (1-based from the bottom) and the second player the even
+/// rows, so threats on those rows are worth more.
+pub fn evaluate(board: &Board, piece: u8) -> i32 {
+    let score = side_score(board, 1) - side_score(board, 2) + zugzwang_score(board);
+    let score = score.clamp(-MAX_EVAL, MAX_EVAL);
+
+    if piece == 1 { score } else { -score }
+}
+
+/// Material score for `piece`, ignoring the opponent.
+fn side_score(board: &Board, piece: u8) -> i32 {
+    let mut score = 0;
+
+    for (col, weight) in CENTER.iter().enumerate() {
+        for row in 0..HEIGHT {
+            if board.piece_at(col, row) == piece {
+                score += weight;
+            }
+        }
+    }
+
+    for window in windows() {
+        let mine = window.iter().filter(|&&(c, r)| board.piece_at(c, r) == piece).count();
+        let empty = window.iter().filter(|&&(c, r)| board.piece_at(c, r) == 0).count();
+        if mine + empty != 4 {
+            continue
+        }
+
+        score += match mine {
+            3 => OPEN_THREE,
+            2 => OPEN_TWO,
+            _ => 0
+        };
+    }
+
+    for (_, row) in board.threats(piece) {
+        score += if good_parity(piece, row) { GOOD_THREAT } else { OTHER_THREAT };
+    }
+
+    score
+}
+
+/// Positive when piece 1 holds the zugzwang, negative when piece 2 does.
+fn zugzwang_score(board: &Board) -> i32 {
+    let first_odd = board.threats(1).iter().any(|&(_, row)| good_parity(1, row));
+    let second_even = board.threats(2).iter().any(|&(_, row)| good_parity(2, row));
+
+    match (first_odd, second_even) {
+        (true, false) => ZUGZWANG,
+        (false, true) => -ZUGZWANG,
+        _ => 0
+    }
+}
+
+/// Odd rows favour piece 1 and even rows piece 2, counting rows from 1 at the bottom.
+fn good_parity(piece: u8, row: usize) -> bool {
+    let odd = (row + 1) % 2 == 1;
+    if piece == 1 { odd } else { !odd }
+}
+
+/// Every line of four cells on the board as `(col, row)` pairs.
+fn windows() -> &'static [[(usize, usize); 4]] {
+    static WINDOWS: OnceLock<Vec<[(usize, usize); 4]>> = OnceLock::new();
+    WINDOWS.get_or_init(build_windows)
+}
+
+fn build_windows() -> Vec<[(usize, usize); 4]> {
+    let mut windows = Vec::new();
+    for col in 0..WIDTH as isize {
+        for row in 0..HEIGHT as isize {
+            for (dc, dr) in DIRECTIONS {
+                let end_col = col + 3 * dc;
+                let end_row = row + 3 * dr;
+                if end_col < 0 || end_col >= WIDTH as isize || end_row < 0 || end_row >= HEIGHT as isize {
+                    continue
+                }
+
+                let mut window = [(0, 0); 4];
+                for (i, cell) in window.iter_mut().enumerate() {
+                    let i = i as isize;
+                    *cell = ((col + i * dc) as usize, (row + i * dr) as usize);
+                }
+                windows.push(window);
+            }
+        }
+    }
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_board_has_69_windows() {
+        assert_eq!(windows().len(), 69);
+    }
+
+    #[test]
+    fn test_empty_board_is_even() {
+        assert_eq!(evaluate(&Board::new(), 1), 0);
+    }
+
+    #[test]
+    fn test_center_piece_favours_its_owner() {
+        let mut board = Board::new();
+        let _ = board.place(3, 1);
+        assert!(evaluate(&board, 1) > 0);
+        assert_eq!(evaluate(&board, 2), -evaluate(&board, 1));
+    }
+
+    #[test]
+    fn test_open_three_outweighs_scattered_pieces() {
+        let mut board = Board::new();
+        for col in 1..4 {
+            let _ = board.place(col, 1);
+        }
+        let _ = board.place(6, 2);
+        let _ = board.place(6, 2);
+        assert!(evaluate(&board, 2) < -OPEN_THREE);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::board::Board;
-use rand::seq::IteratorRandom;
+use crate::engine::Engine;
+use rand::{seq::IteratorRandom, Rng};
 
 pub struct Game {
     pub board: Board,
@@ -14,14 +15,14 @@ impl Game {
         }
     }
 
-    pub fn generate() -> Game {
+    /// Plays a game of uniformly random moves drawn from `rng`.
+    pub fn generate<R: Rng>(rng: &mut R) -> Game {
         let mut game = Self::new();
-        let mut rng = rand::thread_rng();
         let mut turn = 1;
 
         while !game.board.finished() {
             let cols = game.board.available_columns();
-            let move_col = cols.iter().choose(&mut rng).unwrap();
+            let move_col = cols.iter().choose(rng).unwrap();
 
             match game.board.place(*move_col, turn) {
                 Ok(()) => {
@@ -35,6 +36,32 @@ impl Game {
         game
     }
 
+    /// Plays a full game between two engines, `first` playing piece 1.
+    pub fn play(first: &mut dyn Engine, second: &mut dyn Engine) -> Game {
+        let mut game = Self::new();
+        let mut turn = 1;
+
+        while !game.board.finished() {
+            let engine: &mut dyn Engine = if turn == 1 { &mut *first } else { &mut *second };
+            let move_col = engine.choose_move(&game.board, turn);
+
+            match game.board.place(move_col, turn) {
+                Ok(()) => {
+                    game.moves.push((turn, move_col));
+                    turn ^= 3;
+                },
+                Err(err) => panic!("{} made an illegal move: {:?}", engine.name(), err)
+            }
+        }
+
+        game
+    }
+
+    /// Moves as a string of 1-based column digits, e.g. `4453`.
+    pub fn move_string(&self) -> String {
+        self.moves.iter().map(|(_, col)| char::from(b'1' + *col as u8)).collect()
+    }
+
     pub fn print(&self) {
         self.board.print();
         println!("Winner: {:?}", Board::rune_for_piece(self.board.winner().unwrap_or(0)));
@@ -1,15 +1,39 @@
+#[cfg(feature = "torch")]
+mod arena;
 mod board;
+mod book;
+#[cfg(feature = "torch")]
 mod bot;
+mod dataset;
+mod difficulty;
+mod engine;
+mod eval;
 mod game;
+mod mlp;
+mod search;
+mod selfplay;
 mod server;
 mod client;
+mod tui;
 
 use crate::board::Board;
+use crate::book::OpeningBook;
+#[cfg(feature = "torch")]
+use crate::bot::config::{Algorithm, TrainConfig};
 use crate::client::Client;
+use crate::dataset::DatasetConfig;
+use crate::difficulty::Difficulty;
+use crate::engine::{Analysis, Engine, RandomEngine};
 use crate::game::Game;
+use crate::mlp::{Inference, Mlp, MlpEngine};
+use crate::selfplay::SelfPlayConfig;
+use crate::tui::{Player, Tui};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 use std::env;
 use std::io::{self, BufRead};
 use std::net::TcpStream;
+use std::thread;
+use std::time::Duration;
 
 fn main() -> io::Result<()> {
     let args: Vec<String> = env::args().collect();
@@ -29,13 +53,72 @@ fn main() -> io::Result<()> {
         let stream = TcpStream::connect(address)?;
         Client::new(stream, 2).process()?
     } else if args[1] == "generate" {
-        let game = Game::generate();
-        game.print();
+        // A lone `--seed` prints a single game; any other option selects batch generation,
+        // which rejects options it doesn't know.
+        let single = args.len() == 2 || (args.len() == 4 && args[2] == "--seed");
+        if !single {
+            generate(&args[2..]);
+        } else {
+            let Some(seed) = seed_flag(&args) else { return Ok(()) };
+            let mut rng = seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+            let game = Game::generate(&mut rng);
+            game.print();
+        }
+    } else if args[1] == "dataset" {
+        dataset(&args[2..]);
+    } else if args[1] == "book" {
+        build_book(&args[2..])?;
+    } else if args[1] == "export" {
+        #[cfg(feature = "torch")]
+        export(&args[2..]);
+        #[cfg(not(feature = "torch"))]
+        needs_torch(&args[1]);
+    } else if args[1] == "watch" {
+        watch(&args[2..]);
     } else if args[1] == "train" {
-        let _ = bot::bot::train();
+        #[cfg(feature = "torch")]
+        train(&args[2..]);
+        #[cfg(not(feature = "torch"))]
+        needs_torch(&args[1]);
+    } else if args[1] == "eval" {
+        #[cfg(feature = "torch")]
+        evaluate(&args[2..]);
+        #[cfg(not(feature = "torch"))]
+        needs_torch(&args[1]);
+    } else if args[1] == "tui" {
+        tui(&args[2..])?;
     } else if args[1] == "bot" {
+        let inference = if has_flag(&args, "--greedy") {
+            Inference::Greedy
+        } else {
+            match flag_value(&args, "--temperature").map(|t| t.parse::<f64>()) {
+                None => Inference::Sample { temperature: 1.0 },
+                Some(Ok(temperature)) if temperature > 0.0 => Inference::Sample { temperature },
+                Some(_) => {
+                    println!("Expected a positive number for --temperature");
+                    return Ok(())
+                }
+            }
+        };
+
+        let Some(seed) = seed_flag(&args) else { return Ok(()) };
+
+        let (mut engine, header): (Box<dyn Engine>, String) = match flag_value(&args, "--difficulty") {
+            Some(name) => match Difficulty::parse(name) {
+                Some(difficulty) => (difficulty.engine(seed.unwrap_or_else(rand::random)), difficulty.description()),
+                None => {
+                    println!("Unknown difficulty: {}. Choose beginner, intermediate, expert or master.", name);
+                    return Ok(())
+                }
+            },
+            None => network_opponent(inference, seed)
+        };
+
+        let Some(bot_piece) = bot_piece_flag(&args, seed) else { return Ok(()) };
+
+        let verbose = has_flag(&args, "--verbose");
         let mut reader = io::stdin().lock();
-        play_bot(&mut reader)?
+        play_bot(&mut reader, engine.as_mut(), &header, bot_piece, verbose)?
     } else {
         println!("Unknown command: {}", args[1]);
     }
@@ -43,6 +126,38 @@ fn main() -> io::Result<()> {
     Ok(())
 }
 
+/// The network `bot` mode plays without `--difficulty`: `model.ot` in builds with
+/// the torch feature, else the exported `model.c4nn`, else random moves.
+fn network_opponent(inference: Inference, seed: Option<u64>) -> (Box<dyn Engine>, String) {
+    #[cfg(feature = "torch")]
+    {
+        match bot::bot::Bot::open("model.ot") {
+            Ok(mut bot) => {
+                bot.set_inference(inference);
+                if let Some(seed) = seed {
+                    bot.seed(seed);
+                }
+                return (Box::new(bot), String::from("Network: model.ot"))
+            },
+            Err(err) => println!("Could not load model.ot ({}). Train one with `train`.", err)
+        }
+    }
+
+    match Mlp::load("model.c4nn") {
+        Ok(mlp) => {
+            let mut engine = MlpEngine::new(mlp, seed.unwrap_or_else(rand::random));
+            engine.set_inference(inference);
+            (Box::new(engine), String::from("Network: model.c4nn"))
+        },
+        Err(err) => {
+            println!("Could not load model.c4nn ({}). Export one with `export`.", err);
+            println!("Falling back to an opponent that plays random moves.");
+            let random = seed.map_or_else(RandomEngine::new, RandomEngine::seeded);
+            (Box::new(random), String::from("Random moves"))
+        }
+    }
+}
+
 fn play(reader: &mut dyn BufRead) -> io::Result<()> {
     let mut board = Board::new();
     let mut piece: u8 = 1;
@@ -80,63 +195,460 @@ fn play(reader: &mut dyn BufRead) -> io::Result<()> {
     Ok(())
 }
 
-fn play_bot(reader: &mut dyn BufRead) -> io::Result<()> {
+/// Handles `generate --games <n> [--workers <n>] [--seed <n>] [--first <engine>] [--second <engine>] [--output <file>]`.
+fn generate(args: &[String]) {
+    let mut config = SelfPlayConfig::default();
+
+    for pair in args.chunks(2) {
+        let value = match pair.get(1) {
+            Some(value) => value.clone(),
+            None => {
+                println!("Expected a value for {}", pair[0]);
+                return
+            }
+        };
+
+        let parsed = match pair[0].as_str() {
+            "--games" => value.parse().map(|games| config.games = games).is_ok(),
+            "--workers" => value.parse().map(|workers| config.workers = workers).is_ok(),
+            "--seed" => value.parse().map(|seed| config.seed = seed).is_ok(),
+            "--first" => {
+                config.first = value;
+                true
+            },
+            "--second" => {
+                config.second = value;
+                true
+            },
+            "--output" => {
+                config.output = value;
+                true
+            },
+            _ => {
+                println!("Unknown option: {}", pair[0]);
+                return
+            }
+        };
+
+        if !parsed {
+            println!("Invalid value for {}", pair[0]);
+            return
+        }
+    }
+
+    match selfplay::generate(&config) {
+        Ok(written) => println!("Wrote {} games to {}", written, config.output),
+        Err(err) => println!("Generation failed: {}", err)
+    }
+}
+
+/// Handles `dataset [--positions <n>] [--max-plies <n>] [--depth <n>] [--workers <n>] [--seed <n>] [--output <file>]`,
+/// writing labelled positions as CSV, or JSON lines for a `.jsonl` output.
+fn dataset(args: &[String]) {
+    let mut config = DatasetConfig::default();
+
+    for pair in args.chunks(2) {
+        let value = match pair.get(1) {
+            Some(value) => value.clone(),
+            None => {
+                println!("Expected a value for {}", pair[0]);
+                return
+            }
+        };
+
+        let parsed = match pair[0].as_str() {
+            "--positions" => value.parse().map(|positions| config.positions = positions).is_ok(),
+            "--max-plies" => value.parse().map(|plies| config.max_plies = plies).is_ok(),
+            "--depth" => value.parse().map(|depth| config.depth = depth).is_ok(),
+            "--workers" => value.parse().map(|workers| config.workers = workers).is_ok(),
+            "--seed" => value.parse().map(|seed| config.seed = seed).is_ok(),
+            "--output" => {
+                config.output = value;
+                true
+            },
+            _ => {
+                println!("Unknown option: {}", pair[0]);
+                return
+            }
+        };
+
+        if !parsed {
+            println!("Invalid value for {}", pair[0]);
+            return
+        }
+    }
+
+    match dataset::generate(&config) {
+        Ok(written) => println!("Wrote {} positions to {}", written, config.output),
+        Err(err) => println!("Generation failed: {}", err)
+    }
+}
+
+/// Handles `book [--plies <n>] [--search-depth <n>] [--output <file>]`.
+fn build_book(args: &[String]) -> io::Result<()> {
+    let plies = flag_value(args, "--plies").map_or(Ok(4), |v| v.parse::<u8>());
+    let search_depth = flag_value(args, "--search-depth").map_or(Ok(8), |v| v.parse::<u32>());
+    let output = flag_value(args, "--output").unwrap_or("book.bin");
+
+    let (Ok(plies), Ok(search_depth)) = (plies, search_depth) else {
+        println!("Expected numbers for --plies and --search-depth");
+        return Ok(())
+    };
+
+    let book = OpeningBook::build(plies, search_depth);
+    book.save(output)?;
+    println!("Wrote {} positions up to ply {} to {}", book.len(), book.depth(), output);
+    Ok(())
+}
+
+/// Reports a subcommand that only exists in builds with the torch feature.
+#[cfg(not(feature = "torch"))]
+fn needs_torch(command: &str) {
+    println!("`{}` needs libtorch. Rebuild with the torch feature, which is on by default.", command);
+}
+
+/// Handles `export [--model <file>] [--config <file>] [--output <file>]`, converting
+/// a trained model to the portable weights format. `--config` supplies the
+/// architecture for models saved without metadata.
+#[cfg(feature = "torch")]
+fn export(args: &[String]) {
+    let model = flag_value(args, "--model").unwrap_or("model.ot");
+    let output = flag_value(args, "--output").unwrap_or("model.c4nn");
+    let loaded = match flag_value(args, "--config").map(TrainConfig::load) {
+        None => bot::bot::Bot::open(model),
+        Some(Ok(config)) => {
+            let mut bot = bot::bot::Bot::with_architecture(&config.architecture);
+            bot.load(model).map(|_| bot)
+        },
+        Some(Err(err)) => {
+            println!("Could not read config: {}", err);
+            return
+        }
+    };
+
+    let bot = match loaded {
+        Ok(bot) => bot,
+        Err(err) => {
+            println!("Could not load {}: {}", model, err);
+            return
+        }
+    };
+    match bot.export(output) {
+        Ok(()) => println!("Exported {} to {}", model, output),
+        Err(err) => println!("Export failed: {}", err)
+    }
+}
+
+/// Handles `watch <first> <second> [--delay <ms>] [--seed <n>]`, playing two
+/// engine specs against each other and printing every move.
+fn watch(args: &[String]) {
+    if args.len() < 2 || args[0].starts_with("--") || args[1].starts_with("--") {
+        println!("Expected two engine specs, e.g. watch model:model.ot random");
+        return
+    }
+
+    let Ok(delay) = flag_value(args, "--delay").map_or(Ok(500), |v| v.parse::<u64>()) else {
+        println!("Expected a number of milliseconds for --delay");
+        return
+    };
+    let Some(seed) = seed_flag(args) else { return };
+    let seed = seed.unwrap_or_else(rand::random);
+
+    let engines = (engine::from_spec(&args[0], seed), engine::from_spec(&args[1], seed ^ u64::MAX));
+    let (mut first, mut second) = match engines {
+        (Ok(first), Ok(second)) => (first, second),
+        (Err(err), _) | (_, Err(err)) => {
+            println!("Could not create engine: {}", err);
+            return
+        }
+    };
+
+    println!("=== {} {} vs. {} {} ===", Board::rune_for_piece(1), first.name(), Board::rune_for_piece(2), second.name());
     let mut board = Board::new();
-    let mut bot = bot::bot::Bot::new();
-    bot.load("model.ot");
+    board.print();
 
-    let mut buffer = String::new();
+    loop {
+        let piece = board.to_move();
+        let engine = if piece == 1 { first.as_mut() } else { second.as_mut() };
 
-    println!("Input a column 1-7");
+        if let Some(analysis) = engine.analyze(&board, piece) {
+            let line: Vec<String> = analysis.policy.iter().enumerate().map(|(col, p)| format!("{}: {:.2}", col + 1, p)).collect();
+            println!("{} probabilities  {}", Board::rune_for_piece(piece), line.join("  "));
+            if let Some(value) = analysis.value {
+                println!("{} value {:+.2}", Board::rune_for_piece(piece), value);
+            }
+        }
 
-    // TODO: clean this up
-    while let Ok(_) = reader.read_line(&mut buffer) {
-        let col = buffer.trim().parse::<usize>();
+        let col = engine.choose_move(&board, piece);
+        if board.place(col, piece).is_err() {
+            println!("{} made an illegal move in column {}", engine.name(), col + 1);
+            return
+        }
+        println!("{} {} plays column {}", Board::rune_for_piece(piece), engine.name(), col + 1);
 
-        buffer.clear();
+        board.print();
+        if report_result(&board) {
+            return
+        }
+        thread::sleep(Duration::from_millis(delay));
+    }
+}
 
-        if col.is_err() {
-            print_column_error();
-            continue;
+/// Handles `eval [--model <file>] [--games <n>] [--opponents <spec,...>] [--greedy] [--seed <n>]
+/// [--opening-plies <n>]`, playing the model against each opponent as both colors and printing
+/// a table of results. Every game starts with `--opening-plies` random moves, 2 by default,
+/// so a greedy model against a deterministic opponent doesn't replay a single game.
+#[cfg(feature = "torch")]
+fn evaluate(args: &[String]) {
+    let model = flag_value(args, "--model").unwrap_or("model.ot");
+    let Ok(games) = flag_value(args, "--games").map_or(Ok(100), |v| v.parse::<usize>()) else {
+        println!("Expected a number of games for --games");
+        return
+    };
+    let Ok(opening_plies) = flag_value(args, "--opening-plies").map_or(Ok(2), |v| v.parse::<usize>()) else {
+        println!("Expected a number of moves for --opening-plies");
+        return
+    };
+    let opponents = flag_value(args, "--opponents").unwrap_or("random,greedy,search:4");
+    let Some(seed) = seed_flag(args) else { return };
+    let seed = seed.unwrap_or_else(rand::random);
+
+    let mut bot = match bot::bot::Bot::open(model) {
+        Ok(bot) => bot,
+        Err(err) => {
+            println!("Could not load {}: {}", model, err);
+            return
         }
+    };
+    bot.seed(seed);
+    if has_flag(args, "--greedy") {
+        bot.set_inference(Inference::Greedy);
+    }
 
-        match board.place(col.unwrap() - 1, 1) {
-            Ok(_) => {
-                board.print();
-                if let Some(winner) = board.winner() {
-                    println!("{} {}", Board::rune_for_piece(winner), "wins!");
-                    return Ok(())
-                } else if board.full() {
-                    println!("{}", "No more available slots remain. Result is a draw.");
-                    return Ok(())
-                }
+    println!("{:<12} {:<6} {:>6} {:>6} {:>6} {:>6} {:>8} {:>11}", "opponent", "color", "games", "win", "draw", "loss", "illegal", "avg length");
+    for (i, spec) in opponents.split(',').map(str::trim).enumerate() {
+        let mut opponent = match engine::from_spec(spec, seed.wrapping_add(i as u64 + 1)) {
+            Ok(opponent) => opponent,
+            Err(err) => {
+                println!("Could not create engine: {}", err);
+                return
+            }
+        };
+
+        for (piece, color) in [(1, "first"), (2, "second")] {
+            // Both colors start from the same openings.
+            let mut openings = arena::Openings::new(opening_plies, seed);
+            let record = arena::play_match(&mut bot, opponent.as_mut(), piece, games, &mut openings);
+            println!(
+                "{:<12} {:<6} {:>6} {:>6.2} {:>6.2} {:>6.2} {:>8} {:>11.1}",
+                spec,
+                color,
+                record.games,
+                record.rate(record.wins),
+                record.rate(record.draws),
+                record.rate(record.losses),
+                record.illegal_moves,
+                record.avg_length()
+            );
+        }
+    }
+}
+
+/// Handles `train [--config <file>] [--resume <checkpoint>] [--algo pg|dqn|ppo] [--<key> <value>...]`.
+/// With `--supervised <dataset>` the network learns from labelled positions instead,
+/// always from scratch and without a reinforcement learning algorithm.
+#[cfg(feature = "torch")]
+fn train(args: &[String]) {
+    let mut config = match flag_value(args, "--config") {
+        Some(path) => match TrainConfig::load(path) {
+            Ok(config) => config,
+            Err(err) => {
+                println!("Could not read config {}: {}", path, err);
+                return
+            }
+        },
+        None => TrainConfig::default()
+    };
+
+    for pair in args.chunks(2) {
+        let Some(key) = pair[0].strip_prefix("--") else {
+            println!("Unknown option: {}", pair[0]);
+            return
+        };
+        // A value that looks like another flag means this one is missing its own.
+        let Some(value) = pair.get(1).filter(|value| !value.starts_with("--")) else {
+            println!("Expected a value for {}", pair[0]);
+            return
+        };
+        if key == "config" || key == "resume" || key == "supervised" {
+            continue
+        }
+
+        if let Err(err) = config.set(key, value) {
+            println!("Invalid training option: {}", err);
+            return
+        }
+    }
+
+    let resume = flag_value(args, "--resume");
+    if has_flag(args, "--supervised") && (resume.is_some() || has_flag(args, "--algo")) {
+        println!("--supervised cannot be combined with --resume or --algo");
+        return
+    }
+
+    let result = match (flag_value(args, "--supervised"), config.algorithm) {
+        (Some(dataset), _) => bot::supervised::train(&config, dataset),
+        (None, Algorithm::PolicyGradient) => bot::bot::train(&config, resume),
+        (None, Algorithm::Dqn) => bot::dqn::train(&config, resume),
+        (None, Algorithm::Ppo) => bot::ppo::train(&config, resume)
+    };
+    if let Err(err) = result {
+        println!("Training failed: {}", err);
+    }
+}
+
+/// Handles `tui [--bot <engine> [--first human|bot|random]] [--host] [--connect <address>] [--seed <n>]`.
+/// Without options two people take turns at the same keyboard.
+fn tui(args: &[String]) -> io::Result<()> {
+    let Some(seed) = seed_flag(args) else { return Ok(()) };
+    let you = || (String::from("You"), Player::Human);
+
+    let players = if let Some(address) = flag_value(args, "--connect") {
+        [(address.to_string(), Player::Remote(TcpStream::connect(address)?)), you()]
+    } else if has_flag(args, "--host") {
+        let stream = server::accept()?;
+        [you(), (stream.peer_addr()?.to_string(), Player::Remote(stream))]
+    } else if let Some(spec) = flag_value(args, "--bot") {
+        let engine = match engine::from_spec(spec, seed.unwrap_or_else(rand::random)) {
+            Ok(engine) => engine,
+            Err(err) => {
+                println!("Could not create engine: {}", err);
+                return Ok(())
+            }
+        };
+        let Some(bot_piece) = bot_piece_flag(args, seed) else { return Ok(()) };
+        let bot = (engine.name(), Player::engine(engine));
+        if bot_piece == 1 { [bot, you()] } else { [you(), bot] }
+    } else {
+        [(String::from("Player 1"), Player::Human), (String::from("Player 2"), Player::Human)]
+    };
+
+    Tui::new(players).run()
+}
 
-                println!("Bot is thinking...");
-
-                let bot_move = bot.predict(&board);
-                match board.place(bot_move as usize, 2) {
-                    Ok(_) => {
-                        board.print();
-                        if let Some(winner) = board.winner() {
-                            println!("{} {}", Board::rune_for_piece(winner), "wins!");
-                            return Ok(())
-                        } else if board.full() {
-                            println!("{}", "No more available slots remain. Result is a draw.");
-                            return Ok(())
-                        }
-                    },
-                    Err(_) => {
-                        print_column_error();
-                    }
+/// Parses `--first human|bot|random` into the piece the bot plays. Returns `None`
+/// after reporting an invalid value.
+fn bot_piece_flag(args: &[String], seed: Option<u64>) -> Option<u8> {
+    match flag_value(args, "--first").unwrap_or("human") {
+        "human" => Some(2),
+        "bot" => Some(1),
+        "random" => {
+            let mut rng = seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+            Some(if rng.gen_bool(0.5) { 1 } else { 2 })
+        },
+        other => {
+            println!("Unknown value for --first: {}. Choose bot, human or random.", other);
+            None
+        }
+    }
+}
+
+/// Parses an optional `--seed <n>`. Returns `None` after reporting an invalid value.
+fn seed_flag(args: &[String]) -> Option<Option<u64>> {
+    match flag_value(args, "--seed").map(|seed| seed.parse::<u64>()) {
+        None => Some(None),
+        Some(Ok(seed)) => Some(Some(seed)),
+        Some(Err(_)) => {
+            println!("Expected a non-negative integer for --seed");
+            None
+        }
+    }
+}
+
+fn has_flag(args: &[String], flag: &str) -> bool {
+    args.iter().any(|arg| arg == flag)
+}
+
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter().position(|arg| arg == flag).and_then(|i| args.get(i + 1)).map(|v| v.as_str())
+}
+
+/// Plays a human against `engine`. With `verbose`, the engine's analysis of each
+/// position it moves in is shown under the board, or a note if it has none.
+fn play_bot(reader: &mut dyn BufRead, engine: &mut dyn Engine, header: &str, bot_piece: u8, mut verbose: bool) -> io::Result<()> {
+    let mut board = Board::new();
+    let mut buffer = String::new();
+
+    println!("=== Connect Four vs. {} ===", header);
+    println!("You play {}. Input a column 1-7", Board::rune_for_piece(bot_piece ^ 3));
+
+    loop {
+        let mut analysis = None;
+
+        if board.to_move() == bot_piece {
+            println!("Bot is thinking...");
+
+            if verbose {
+                analysis = engine.analyze(&board, bot_piece);
+                if analysis.is_none() {
+                    println!("{} has no analysis to show, ignoring --verbose.", engine.name());
+                    verbose = false;
                 }
+            }
+            let bot_move = engine.choose_move(&board, bot_piece);
+            if board.place(bot_move, bot_piece).is_err() {
+                println!("{} made an illegal move in column {}", engine.name(), bot_move + 1);
+                return Ok(())
+            }
+            println!("Bot plays column {}", bot_move + 1);
+        } else {
+            if reader.read_line(&mut buffer)? == 0 {
+                return Ok(())
+            }
+            let col = buffer.trim().parse::<usize>();
 
-            },
-            Err(_) => {
+            buffer.clear();
+
+            if col.is_err() || board.place(col.unwrap().wrapping_sub(1), bot_piece ^ 3).is_err() {
                 print_column_error();
+                continue;
             }
         }
+
+        board.print();
+        if let Some(analysis) = analysis {
+            print_analysis(&analysis);
+        }
+        if report_result(&board) {
+            return Ok(())
+        }
+    }
+}
+
+/// Prints a bar per column lined up with `Board::print`, then the percentages.
+fn print_analysis(analysis: &Analysis) {
+    const BARS: [char; 9] = [' ', '▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let bars: String = analysis.policy.iter().map(|p| format!("{} ", BARS[(p * 8.0).round() as usize])).collect();
+    let percents: Vec<String> = analysis.policy.iter().map(|p| format!("{:.0}%", p * 100.0)).collect();
+    println!("{}", bars);
+    match analysis.value {
+        Some(value) => println!("Policy: {}  Value: {:+.2}", percents.join(" "), value),
+        None => println!("Policy: {}", percents.join(" "))
+    }
+}
+
+/// Prints the outcome once the game is over and returns whether it is.
+fn report_result(board: &Board) -> bool {
+    if let Some(winner) = board.winner() {
+        println!("{} wins!", Board::rune_for_piece(winner));
+        true
+    } else if board.full() {
+        println!("No more available slots remain. Result is a draw.");
+        true
+    } else {
+        false
     }
-    Ok(())
 }
 
 fn print_column_error() {
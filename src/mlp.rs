@@ -0,0 +1,282 @@
+use crate::board::{Board, HEIGHT, WIDTH};
+use crate::engine::{Analysis, Engine};
+use rand::{distributions::WeightedIndex, prelude::Distribution, rngs::StdRng, SeedableRng};
+use std::fs;
+use std::io;
+
+const MAGIC: &[u8; 4] = b"C4NN";
+const VERSION: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Activation {
+    Tanh,
+    Relu
+}
+
+/// How a network turns its policy into a column.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Inference {
+    /// Always play the most probable legal column.
+    Greedy,
+    /// Sample a legal column from the softmax of the logits divided by `temperature`.
+    Sample { temperature: f64 }
+}
+
+impl Activation {
+    fn apply(&self, x: f32) -> f32 {
+        match self {
+            Activation::Tanh => x.tanh(),
+            Activation::Relu => x.max(0.0)
+        }
+    }
+}
+
+/// A fully connected layer. `weights` is row-major with one row of `inputs`
+/// values per output, the same layout as `tch::nn::Linear`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layer {
+    pub inputs: usize,
+    pub outputs: usize,
+    pub weights: Vec<f32>,
+    pub bias: Vec<f32>
+}
+
+/// The bot's policy network evaluated in plain Rust, so exported models can be
+/// played without libtorch.
+///
+/// On disk a weights file is the magic `C4NN`, a version byte, the activation
+/// (0 for tanh, 1 for relu) and the layer count, followed by each layer as its
+/// output and input sizes (little-endian `u32`), the weights and then the bias
+/// (little-endian `f32`). The activation follows every layer but the last.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mlp {
+    pub activation: Activation,
+    pub layers: Vec<Layer>
+}
+
+impl Mlp {
+    pub fn forward(&self, input: &[f32]) -> Vec<f32> {
+        let mut xs = input.to_vec();
+        for (i, layer) in self.layers.iter().enumerate() {
+            let last = i + 1 == self.layers.len();
+            xs = (0..layer.outputs)
+                .map(|out| {
+                    let row = &layer.weights[out * layer.inputs..(out + 1) * layer.inputs];
+                    let sum = layer.bias[out] + row.iter().zip(&xs).map(|(w, x)| w * x).sum::<f32>();
+                    if last { sum } else { self.activation.apply(sum) }
+                })
+                .collect();
+        }
+        xs
+    }
+
+    /// Softmax of the logits for `piece` over the legal columns at `temperature`.
+    pub fn policy(&self, board: &Board, piece: u8, temperature: f64) -> Vec<f64> {
+        let logits = self.forward(&encode(board, piece));
+        let legal = board.available_columns();
+        let max = legal.iter().map(|&col| logits[col] as f64).fold(f64::NEG_INFINITY, f64::max);
+
+        let mut probs = [0.0; WIDTH];
+        for &col in &legal {
+            probs[col] = ((logits[col] as f64 - max) / temperature).exp();
+        }
+        let total: f64 = probs.iter().sum();
+        probs.iter().map(|p| p / total).collect()
+    }
+
+    // Only `Bot::export` writes networks.
+    #[cfg_attr(not(feature = "torch"), allow(dead_code))]
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(match self.activation {
+            Activation::Tanh => 0,
+            Activation::Relu => 1
+        });
+        bytes.push(self.layers.len() as u8);
+        for layer in &self.layers {
+            bytes.extend_from_slice(&(layer.outputs as u32).to_le_bytes());
+            bytes.extend_from_slice(&(layer.inputs as u32).to_le_bytes());
+            for value in layer.weights.iter().chain(&layer.bias) {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        fs::write(path, bytes)
+    }
+
+    pub fn load(path: &str) -> io::Result<Mlp> {
+        let bytes = fs::read(path)?;
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, msg));
+
+        if bytes.len() < 7 || &bytes[..4] != MAGIC {
+            return Err(invalid("not a weights file"))
+        }
+        if bytes[4] != VERSION {
+            return Err(invalid("unsupported weights version"))
+        }
+        let activation = match bytes[5] {
+            0 => Activation::Tanh,
+            1 => Activation::Relu,
+            _ => return Err(invalid("unknown activation"))
+        };
+
+        let mut rest = &bytes[7..];
+        let mut take = |len: usize| {
+            if rest.len() < len {
+                return Err(invalid("truncated weights file"))
+            }
+            let (head, tail) = rest.split_at(len);
+            rest = tail;
+            Ok(head)
+        };
+
+        let mut layers = Vec::new();
+        for _ in 0..bytes[6] {
+            let outputs = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+            let inputs = u32::from_le_bytes(take(4)?.try_into().unwrap()) as usize;
+            // The sizes come from the file, so a corrupt header must not overflow.
+            let len = outputs
+                .checked_mul(inputs)
+                .and_then(|weights| weights.checked_add(outputs))
+                .and_then(|values| values.checked_mul(4))
+                .ok_or_else(|| invalid("truncated weights file"))?;
+            let mut values: Vec<f32> = take(len)?
+                .chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+                .collect();
+            let bias = values.split_off(outputs * inputs);
+            layers.push(Layer { inputs, outputs, weights: values, bias });
+        }
+
+        if layers.first().map(|layer| layer.inputs) != Some(WIDTH * HEIGHT) || layers.last().map(|layer| layer.outputs) != Some(WIDTH) {
+            return Err(invalid("network does not fit the board"))
+        }
+        if layers.windows(2).any(|pair| pair[0].outputs != pair[1].inputs) {
+            return Err(invalid("layer sizes do not line up"))
+        }
+
+        Ok(Mlp { activation, layers })
+    }
+}
+
+/// Encodes `board` as the network sees it when playing `piece`: its own pieces
+/// as 2 and the opponent's as 1, matching the side the network is trained on.
+pub fn encode(board: &Board, piece: u8) -> Vec<f32> {
+    board
+        .flatten()
+        .into_iter()
+        .map(|slot| if piece == 1 && slot != 0 { slot ^ 3 } else { slot })
+        .map(f32::from)
+        .collect()
+}
+
+/// Plays an exported network, by default sampling moves from its policy at temperature 1.
+pub struct MlpEngine {
+    mlp: Mlp,
+    inference: Inference,
+    rng: StdRng
+}
+
+impl MlpEngine {
+    pub fn new(mlp: Mlp, seed: u64) -> MlpEngine {
+        MlpEngine { mlp, inference: Inference::Sample { temperature: 1.0 }, rng: StdRng::seed_from_u64(seed) }
+    }
+
+    pub fn set_inference(&mut self, inference: Inference) {
+        self.inference = inference;
+    }
+
+    /// Sampling temperature, 1 for greedy play as in `Bot::analyze`.
+    fn temperature(&self) -> f64 {
+        match self.inference {
+            Inference::Greedy => 1.0,
+            Inference::Sample { temperature } => temperature
+        }
+    }
+}
+
+impl Engine for MlpEngine {
+    fn name(&self) -> String {
+        String::from("weights")
+    }
+
+    fn choose_move(&mut self, board: &Board, piece: u8) -> usize {
+        let probs = self.mlp.policy(board, piece, self.temperature());
+        match self.inference {
+            // The first of equally likely columns, as `argmax` picks for a `Bot`.
+            Inference::Greedy => probs.iter().enumerate().fold(0, |best, (col, &p)| if p > probs[best] { col } else { best }),
+            Inference::Sample { .. } => WeightedIndex::new(&probs).unwrap().sample(&mut self.rng)
+        }
+    }
+
+    fn analyze(&mut self, board: &Board, piece: u8) -> Option<Analysis> {
+        Some(Analysis { policy: self.mlp.policy(board, piece, self.temperature()), value: None })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiny() -> Mlp {
+        let inputs = WIDTH * HEIGHT;
+        Mlp {
+            activation: Activation::Relu,
+            layers: vec![
+                Layer { inputs, outputs: 2, weights: (0..2 * inputs).map(|i| i as f32 * 0.01).collect(), bias: vec![0.5, -0.5] },
+                Layer { inputs: 2, outputs: WIDTH, weights: (0..2 * WIDTH).map(|i| i as f32 * -0.1).collect(), bias: vec![0.0; WIDTH] }
+            ]
+        }
+    }
+
+    #[test]
+    fn test_save_and_load_round_trip() {
+        let mlp = tiny();
+        let path = std::env::temp_dir().join("connect_four_mlp_test.bin");
+        let path = path.to_str().unwrap();
+        assert!(mlp.save(path).is_ok());
+
+        let loaded = Mlp::load(path).unwrap();
+        let _ = fs::remove_file(path);
+        assert_eq!(loaded, mlp);
+    }
+
+    #[test]
+    fn test_load_rejects_oversized_layers() {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&[VERSION, 0, 1]);
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        let path = std::env::temp_dir().join("connect_four_mlp_oversized.bin");
+        let path = path.to_str().unwrap();
+        fs::write(path, bytes).unwrap();
+
+        let err = Mlp::load(path).unwrap_err();
+        let _ = fs::remove_file(path);
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_policy_ignores_full_columns() {
+        let mut board = Board::new();
+        for _ in 0..3 {
+            let _ = board.place(0, 1);
+            let _ = board.place(0, 2);
+        }
+        let policy = tiny().policy(&board, 1, 1.0);
+        assert_eq!(policy[0], 0.0);
+        assert!((policy.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_greedy_engine_plays_the_most_probable_column() {
+        let board = Board::new();
+        let policy = tiny().policy(&board, 1, 1.0);
+        let best = (0..WIDTH).max_by(|&a, &b| policy[a].total_cmp(&policy[b])).unwrap();
+
+        let mut engine = MlpEngine::new(tiny(), 0);
+        engine.set_inference(Inference::Greedy);
+        assert_eq!(engine.choose_move(&board, 1), best);
+    }
+}
@@ -0,0 +1,92 @@
+use crate::board::{Board, WIDTH};
+use crate::eval;
+
+/// Score of a position won by the side to move, before subtracting the plies needed to reach it.
+pub const WIN_SCORE: i32 = 1000;
+
+/// Columns ordered from the center out, which tends to produce cutoffs early.
+const MOVE_ORDER: [usize; WIDTH] = [3, 2, 4, 1, 5, 0, 6];
+
+/// Negamax search with alpha-beta pruning, scored from `piece`'s perspective.
+///
+/// Wins score `WIN_SCORE` minus the number of plies played, so faster wins and
+/// slower losses are preferred. Positions still open at `depth` 0 are scored
+/// with `eval::evaluate`.
+pub fn negamax(board: &Board, piece: u8, depth: u32, mut alpha: i32, beta: i32) -> i32 {
+    if board.full() {
+        return 0
+    }
+    if depth == 0 {
+        return eval::evaluate(board, piece)
+    }
+
+    let mut best = -WIN_SCORE;
+    for col in MOVE_ORDER {
+        let mut next = board.clone();
+        if next.place(col, piece).is_err() {
+            continue
+        }
+
+        let score = if next.winner() == Some(piece) {
+            WIN_SCORE - next.plies() as i32
+        } else {
+            -negamax(&next, piece ^ 3, depth - 1, -beta, -alpha)
+        };
+
+        best = best.max(score);
+        alpha = alpha.max(score);
+        if alpha >= beta {
+            break
+        }
+    }
+    best
+}
+
+/// Returns the best column for `piece` and its score, searching `depth` plies ahead.
+/// Ties go to the column closest to the center.
+pub fn best_move(board: &Board, piece: u8, depth: u32) -> Option<(usize, i32)> {
+    let mut best: Option<(usize, i32)> = None;
+    for col in MOVE_ORDER {
+        let mut next = board.clone();
+        if next.place(col, piece).is_err() {
+            continue
+        }
+
+        let score = if next.winner() == Some(piece) {
+            WIN_SCORE - next.plies() as i32
+        } else {
+            let alpha = best.map_or(-WIN_SCORE, |(_, score)| score);
+            -negamax(&next, piece ^ 3, depth.saturating_sub(1), -WIN_SCORE, -alpha)
+        };
+
+        if best.is_none_or(|(_, best_score)| score > best_score) {
+            best = Some((col, score));
+        }
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_takes_immediate_win() {
+        let mut board = Board::new();
+        for col in 0..3 {
+            let _ = board.place(col, 1);
+            let _ = board.place(col, 2);
+        }
+        assert_eq!(best_move(&board, 1, 2).unwrap().0, 3);
+    }
+
+    #[test]
+    fn test_blocks_immediate_loss() {
+        let mut board = Board::new();
+        for col in 0..3 {
+            let _ = board.place(col, 2);
+        }
+        let _ = board.place(6, 1);
+        assert_eq!(best_move(&board, 1, 2).unwrap().0, 3);
+    }
+}
@@ -1,13 +1,16 @@
 use std::io;
-use std::net::TcpListener;
+use std::net::{TcpListener, TcpStream};
 use crate::client::Client;
 
-pub fn listen() -> io::Result<()> {
+/// Waits for a single opponent to connect.
+pub fn accept() -> io::Result<TcpStream> {
     let listener = TcpListener::bind("0.0.0.0:54321")?;
 
     println!("Listening on {:?}", listener.local_addr().unwrap());
 
-    let mut _stream = listener.accept()?;
+    Ok(listener.accept()?.0)
+}
 
-    Client::new(_stream.0, 1).process()
+pub fn listen() -> io::Result<()> {
+    Client::new(accept()?, 1).process()
 }
@@ -0,0 +1,452 @@
+use crate::board::{Board, HEIGHT, WIDTH};
+use crate::engine::Engine;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::io::{self, ErrorKind, Read, Stdout, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const BOARD_X: u16 = 2;
+const BOARD_Y: u16 = 3;
+const SIDEBAR_X: u16 = BOARD_X + 4 * WIDTH as u16 + 4;
+/// Most recent moves listed in the sidebar.
+const MOVE_LINES: usize = 12;
+/// How long input is waited for before the clocks are redrawn.
+const TICK: Duration = Duration::from_millis(100);
+/// Time a falling disc spends in each row.
+const DROP_FRAME: Duration = Duration::from_millis(30);
+
+/// Who makes the moves for one side.
+pub enum Player {
+    /// Someone at this terminal, choosing columns with the keyboard.
+    Human,
+    /// Shared with the worker thread that searches for its moves, see `Player::engine`.
+    Engine(Arc<Mutex<Box<dyn Engine + Send>>>),
+    /// The other end of a network game, exchanging 1-based columns as single bytes
+    /// like `Client`.
+    Remote(TcpStream)
+}
+
+impl Player {
+    pub fn engine(engine: Box<dyn Engine + Send>) -> Player {
+        Player::Engine(Arc::new(Mutex::new(engine)))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Turn {
+    Move(usize),
+    Quit,
+    /// The game cannot go on, e.g. because the remote player disconnected.
+    Abort(String)
+}
+
+/// What a key press asks for.
+#[derive(Debug, PartialEq)]
+enum Action {
+    /// Move the cursor to a column.
+    Select(usize),
+    /// Drop a disc into a column.
+    Drop(usize),
+    Quit
+}
+
+/// Raw mode on the alternate screen, restored when dropped so a panic does not
+/// leave the terminal unusable.
+struct Screen {
+    out: Stdout
+}
+
+impl Screen {
+    fn new() -> io::Result<Screen> {
+        let mut out = io::stdout();
+        terminal::enable_raw_mode()?;
+        execute!(out, EnterAlternateScreen, Hide, Clear(ClearType::All))?;
+        Ok(Screen { out })
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(self.out, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// A full-screen game between two players, piece 1 first.
+pub struct Tui {
+    board: Board,
+    players: [(String, Player); 2],
+    moves: Vec<(u8, usize)>,
+    /// Column the next human move is dropped into.
+    cursor: usize,
+    /// Thinking time used by each side so far.
+    clocks: [Duration; 2],
+    turn_started: Instant,
+    status: String
+}
+
+impl Tui {
+    pub fn new(players: [(String, Player); 2]) -> Tui {
+        Tui {
+            board: Board::new(),
+            players,
+            moves: Vec::new(),
+            cursor: WIDTH / 2,
+            clocks: [Duration::ZERO; 2],
+            turn_started: Instant::now(),
+            status: String::new()
+        }
+    }
+
+    /// Plays the game until it ends or a human quits with `q`, Esc or Ctrl-C.
+    pub fn run(&mut self) -> io::Result<()> {
+        let mut screen = Screen::new()?;
+
+        while !self.board.finished() {
+            let piece = self.board.to_move();
+            self.turn_started = Instant::now();
+            self.status = match &self.players[piece as usize - 1].1 {
+                Player::Human => format!("{} to move", self.players[piece as usize - 1].0),
+                Player::Engine(_) => format!("{} is thinking...", self.players[piece as usize - 1].0),
+                Player::Remote(_) => format!("Waiting for {}...", self.players[piece as usize - 1].0)
+            };
+
+            let col = match self.turn(&mut screen.out, piece)? {
+                Turn::Move(col) => col,
+                Turn::Quit => return Ok(()),
+                Turn::Abort(reason) => {
+                    self.status = reason;
+                    return self.wait_for_key(&mut screen.out)
+                }
+            };
+            self.clocks[piece as usize - 1] += self.turn_started.elapsed();
+
+            self.animate_drop(&mut screen.out, col, piece)?;
+            let _ = self.board.place(col, piece);
+            self.moves.push((piece, col));
+            if let Player::Remote(stream) = &mut self.players[(piece ^ 3) as usize - 1].1 {
+                stream.write_all(&[encode_column(col)])?;
+            }
+        }
+
+        self.status = match self.board.winner() {
+            Some(winner) => format!("{} wins!", self.players[winner as usize - 1].0),
+            None => String::from("No more available slots remain. Result is a draw.")
+        };
+        self.wait_for_key(&mut screen.out)
+    }
+
+    fn turn(&mut self, out: &mut Stdout, piece: u8) -> io::Result<Turn> {
+        match &self.players[piece as usize - 1].1 {
+            Player::Human => self.human_turn(out, piece),
+            Player::Engine(_) => self.engine_turn(out, piece),
+            Player::Remote(_) => self.remote_turn(out)
+        }
+    }
+
+    /// Lets the engine think on a worker thread so the clocks keep running and
+    /// the local player can quit meanwhile. A quit leaves the worker to finish on its own.
+    fn engine_turn(&mut self, out: &mut Stdout, piece: u8) -> io::Result<Turn> {
+        let (name, player) = &self.players[piece as usize - 1];
+        let Player::Engine(engine) = player else { unreachable!() };
+        let (name, engine, board) = (name.clone(), Arc::clone(engine), self.board.clone());
+        let worker = thread::spawn(move || engine.lock().unwrap().choose_move(&board, piece));
+
+        while !worker.is_finished() {
+            self.draw(out, None)?;
+            if poll_key(out, TICK)?.is_some_and(|key| key_action(&key, self.cursor) == Some(Action::Quit)) {
+                return Ok(Turn::Quit)
+            }
+        }
+
+        Ok(match worker.join() {
+            Ok(col) => engine_move(&self.board, &name, col),
+            Err(_) => Turn::Abort(format!("{} crashed.", name))
+        })
+    }
+
+    fn human_turn(&mut self, out: &mut Stdout, piece: u8) -> io::Result<Turn> {
+        loop {
+            self.draw(out, None)?;
+            let Some(key) = poll_key(out, TICK)? else { continue };
+
+            let col = match key_action(&key, self.cursor) {
+                Some(Action::Select(col)) => {
+                    self.cursor = col;
+                    continue
+                },
+                Some(Action::Drop(col)) => col,
+                Some(Action::Quit) => return Ok(Turn::Quit),
+                None => continue
+            };
+
+            self.cursor = col;
+            if legal(&self.board, col) {
+                return Ok(Turn::Move(col))
+            }
+            self.status = format!("Column {} is full, {} to move", col + 1, disc_name(piece));
+        }
+    }
+
+    /// Waits for the remote player's column while keeping the clocks running and
+    /// letting the local player quit.
+    fn remote_turn(&mut self, out: &mut Stdout) -> io::Result<Turn> {
+        loop {
+            self.draw(out, None)?;
+            if poll_key(out, Duration::ZERO)?.is_some_and(|key| key_action(&key, self.cursor) == Some(Action::Quit)) {
+                return Ok(Turn::Quit)
+            }
+
+            let index = self.board.to_move() as usize - 1;
+            let Player::Remote(stream) = &mut self.players[index].1 else { unreachable!() };
+            stream.set_read_timeout(Some(TICK))?;
+            let mut buf = [0; 1];
+            match stream.read(&mut buf) {
+                Ok(0) => return Ok(Turn::Abort(String::from("The opponent disconnected."))),
+                Ok(_) => return Ok(remote_move(&self.board, buf[0])),
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+                Err(err) => return Err(err)
+            }
+        }
+    }
+
+    /// Shows `piece` falling down `col` to where it will land.
+    fn animate_drop(&mut self, out: &mut Stdout, col: usize, piece: u8) -> io::Result<()> {
+        let Some(target) = self.board.first_available_row_for_column(col) else { return Ok(()) };
+        for row in (target..HEIGHT).rev() {
+            self.draw(out, Some((col, row, piece)))?;
+            thread::sleep(DROP_FRAME);
+        }
+        Ok(())
+    }
+
+    fn wait_for_key(&mut self, out: &mut Stdout) -> io::Result<()> {
+        self.status.push_str(" Press any key to exit.");
+        self.draw(out, None)?;
+        loop {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    return Ok(())
+                }
+            }
+        }
+    }
+
+    /// Redraws the whole screen, with an optional disc in flight at `(col, row)`.
+    /// Lines are overwritten in place rather than clearing the screen, which flickers.
+    fn draw(&self, out: &mut Stdout, falling: Option<(usize, usize, u8)>) -> io::Result<()> {
+        let to_move = self.board.to_move();
+        queue!(out, MoveTo(BOARD_X, 0), Print("Connect Four  "))?;
+        draw_disc(out, 1)?;
+        queue!(out, Print(format!(" {}  vs.  ", self.players[0].0)))?;
+        draw_disc(out, 2)?;
+        queue!(out, Print(format!(" {}", self.players[1].0)))?;
+
+        queue!(out, MoveTo(0, BOARD_Y - 1), Clear(ClearType::UntilNewLine))?;
+        let human_to_move = matches!(self.players[to_move as usize - 1].1, Player::Human);
+        if human_to_move && falling.is_none() && !self.board.finished() {
+            queue!(
+                out,
+                MoveTo(BOARD_X + 2 + 4 * self.cursor as u16, BOARD_Y - 1),
+                SetForegroundColor(color(to_move)),
+                Print('▼'),
+                ResetColor
+            )?;
+        }
+
+        for row in (0..HEIGHT).rev() {
+            queue!(out, MoveTo(BOARD_X, BOARD_Y + (HEIGHT - 1 - row) as u16))?;
+            for col in 0..WIDTH {
+                let piece = match falling {
+                    Some((c, r, piece)) if (c, r) == (col, row) => piece,
+                    _ => self.board.piece_at(col, row)
+                };
+                queue!(out, Print("│ "))?;
+                draw_disc(out, piece)?;
+                queue!(out, Print(' '))?;
+            }
+            queue!(out, Print('│'))?;
+        }
+        let bottom = BOARD_Y + HEIGHT as u16;
+        queue!(out, MoveTo(BOARD_X, bottom), Print(format!("└{}───┘", "───┴".repeat(WIDTH - 1))))?;
+        let numbers: String = (1..=WIDTH).map(|col| format!("  {} ", col)).collect();
+        queue!(out, MoveTo(BOARD_X, bottom + 1), Print(numbers))?;
+
+        for piece in [1, 2] {
+            let index = piece as usize - 1;
+            let mut clock = self.clocks[index];
+            if piece == to_move && !self.board.finished() {
+                clock += self.turn_started.elapsed();
+            }
+            queue!(out, MoveTo(SIDEBAR_X, BOARD_Y - 1 + index as u16))?;
+            if piece == to_move && !self.board.finished() {
+                queue!(out, SetAttribute(Attribute::Bold))?;
+            }
+            draw_disc(out, piece)?;
+            queue!(out, Print(format!(" {:<16} {}", self.players[index].0, format_clock(clock))), SetAttribute(Attribute::Reset))?;
+        }
+
+        queue!(out, MoveTo(SIDEBAR_X, BOARD_Y + 2), Print("Moves"))?;
+        let skipped = self.moves.len().saturating_sub(MOVE_LINES);
+        for (line, (ply, &(piece, col))) in self.moves.iter().enumerate().skip(skipped).enumerate() {
+            queue!(out, MoveTo(SIDEBAR_X, BOARD_Y + 3 + line as u16), Clear(ClearType::UntilNewLine), Print(format!("{:>2}. ", ply + 1)))?;
+            draw_disc(out, piece)?;
+            queue!(out, Print(format!(" {}", col + 1)))?;
+        }
+
+        queue!(
+            out,
+            MoveTo(BOARD_X, bottom + 3),
+            Clear(ClearType::UntilNewLine),
+            Print(&self.status),
+            MoveTo(BOARD_X, bottom + 4),
+            SetAttribute(Attribute::Dim),
+            Print("←/→ select  Enter drop  1-7 play  q quit"),
+            SetAttribute(Attribute::Reset)
+        )?;
+        out.flush()
+    }
+}
+
+/// Waits up to `timeout` for a key press, clearing the screen if the terminal
+/// is resized meanwhile so the next draw starts clean.
+fn poll_key(out: &mut Stdout, timeout: Duration) -> io::Result<Option<KeyEvent>> {
+    if !event::poll(timeout)? {
+        return Ok(None)
+    }
+    match event::read()? {
+        Event::Key(key) if key.kind == KeyEventKind::Press => Ok(Some(key)),
+        Event::Resize(..) => {
+            queue!(out, Clear(ClearType::All))?;
+            Ok(None)
+        },
+        _ => Ok(None)
+    }
+}
+
+/// Maps a key to what it does with the cursor at `cursor`. Only presses count,
+/// so terminals reporting releases and repeats don't act twice.
+fn key_action(key: &KeyEvent, cursor: usize) -> Option<Action> {
+    if key.kind != KeyEventKind::Press {
+        return None
+    }
+    match key.code {
+        KeyCode::Left | KeyCode::Char('h') => Some(Action::Select(cursor.saturating_sub(1))),
+        KeyCode::Right | KeyCode::Char('l') => Some(Action::Select((cursor + 1).min(WIDTH - 1))),
+        KeyCode::Char(c @ '1'..='7') => Some(Action::Drop(c as usize - '1' as usize)),
+        KeyCode::Enter | KeyCode::Down | KeyCode::Char(' ') => Some(Action::Drop(cursor)),
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Action::Quit),
+        KeyCode::Char('q') | KeyCode::Esc => Some(Action::Quit),
+        _ => None
+    }
+}
+
+fn legal(board: &Board, col: usize) -> bool {
+    col < WIDTH && board.first_available_row_for_column(col).is_some()
+}
+
+/// The turn for the column `name`'s engine chose.
+fn engine_move(board: &Board, name: &str, col: usize) -> Turn {
+    if legal(board, col) {
+        Turn::Move(col)
+    } else {
+        Turn::Abort(format!("{} made an illegal move in column {}", name, col + 1))
+    }
+}
+
+/// Columns travel over the network as single bytes holding the 1-based column.
+fn encode_column(col: usize) -> u8 {
+    col as u8 + 1
+}
+
+/// The turn for a byte received from the remote player.
+fn remote_move(board: &Board, byte: u8) -> Turn {
+    let col = (byte as usize).wrapping_sub(1);
+    if legal(board, col) {
+        Turn::Move(col)
+    } else {
+        Turn::Abort(format!("The opponent sent an invalid column: {}", byte))
+    }
+}
+
+fn color(piece: u8) -> Color {
+    if piece == 1 { Color::Red } else { Color::Yellow }
+}
+
+fn disc_name(piece: u8) -> &'static str {
+    if piece == 1 { "red" } else { "yellow" }
+}
+
+/// A colored disc for `piece`, or a blank for an empty cell.
+fn draw_disc(out: &mut Stdout, piece: u8) -> io::Result<()> {
+    if piece == 0 {
+        return queue!(out, Print(' '))
+    }
+    queue!(out, SetForegroundColor(color(piece)), Print('●'), ResetColor)
+}
+
+/// Minutes and seconds, e.g. `1:05`.
+fn format_clock(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_keys_move_the_cursor_and_drop() {
+        assert_eq!(key_action(&press(KeyCode::Left), 0), Some(Action::Select(0)));
+        assert_eq!(key_action(&press(KeyCode::Char('l')), 3), Some(Action::Select(4)));
+        assert_eq!(key_action(&press(KeyCode::Right), WIDTH - 1), Some(Action::Select(WIDTH - 1)));
+        assert_eq!(key_action(&press(KeyCode::Enter), 2), Some(Action::Drop(2)));
+        assert_eq!(key_action(&press(KeyCode::Char('7')), 2), Some(Action::Drop(6)));
+        assert_eq!(key_action(&press(KeyCode::Char('8')), 2), None);
+    }
+
+    #[test]
+    fn test_quit_keys_only_count_when_pressed() {
+        assert_eq!(key_action(&press(KeyCode::Char('q')), 3), Some(Action::Quit));
+        assert_eq!(key_action(&press(KeyCode::Esc), 3), Some(Action::Quit));
+        assert_eq!(key_action(&KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL), 3), Some(Action::Quit));
+        assert_eq!(key_action(&press(KeyCode::Char('c')), 3), None);
+
+        let release = KeyEvent { kind: KeyEventKind::Release, ..press(KeyCode::Char('q')) };
+        assert_eq!(key_action(&release, 3), None);
+    }
+
+    #[test]
+    fn test_remote_columns_are_checked() {
+        let mut board = Board::new();
+        for col in 0..WIDTH {
+            assert_eq!(remote_move(&board, encode_column(col)), Turn::Move(col));
+        }
+        assert!(matches!(remote_move(&board, 0), Turn::Abort(_)));
+        assert!(matches!(remote_move(&board, b'4'), Turn::Abort(_)));
+
+        for _ in 0..HEIGHT {
+            let _ = board.place(0, board.to_move());
+        }
+        assert!(matches!(remote_move(&board, encode_column(0)), Turn::Abort(_)));
+        assert!(matches!(engine_move(&board, "bot", 0), Turn::Abort(_)));
+        assert_eq!(engine_move(&board, "bot", 1), Turn::Move(1));
+    }
+
+    #[test]
+    fn test_format_clock() {
+        assert_eq!(format_clock(Duration::from_millis(900)), "0:00");
+        assert_eq!(format_clock(Duration::from_secs(65)), "1:05");
+        assert_eq!(format_clock(Duration::from_secs(600)), "10:00");
+    }
+}